@@ -0,0 +1,30 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! massa-cipher algorithm module.
+//!
+//! Read `lib.rs` module documentation for more information.
+
+use crate::constants::{NONCE_SIZE, SALT_SIZE};
+
+/// AEAD algorithm the data is encrypted with
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherAlgorithm {
+    /// AES-256-GCM
+    Aes256Gcm,
+}
+
+impl CipherAlgorithm {
+    /// Size of the authentication tag appended to the encrypted data
+    pub fn tag_len(&self) -> usize {
+        match self {
+            CipherAlgorithm::Aes256Gcm => 16,
+        }
+    }
+
+    /// Minimum size of the content following the header: a salt, a nonce,
+    /// and the authentication tag of an empty plaintext
+    pub(crate) fn min_content_len(&self) -> usize {
+        SALT_SIZE + NONCE_SIZE + self.tag_len()
+    }
+}
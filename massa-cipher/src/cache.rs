@@ -0,0 +1,133 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! massa-cipher key derivation cache module.
+//!
+//! Deriving the cipher key is deliberately slow. When many encrypted data share the same password
+//! and salt, e.g. when unlocking several files of a wallet more than once, `decrypt_with_cache`
+//! reuses the keys memoized in a `KdfCache` instead of deriving them again.
+//!
+//! Read `lib.rs` module documentation for more information.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::error::CipherError;
+use crate::kdf::Kdf;
+
+/// Cache entry key: the SHA-256 of the password, the salt and the key derivation function
+type CacheKey = ([u8; 32], String, Kdf);
+
+/// Thread-safe cache of derived cipher keys.
+///
+/// The keys are zeroized when they are removed from the cache and when the cache is dropped.
+#[derive(Debug, Default)]
+pub struct KdfCache {
+    keys: Mutex<HashMap<CacheKey, Zeroizing<Vec<u8>>>>,
+    derivations: AtomicUsize,
+}
+
+impl KdfCache {
+    /// Creates an empty `KdfCache`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of keys actually derived through this cache, i.e. of cache misses
+    pub fn derivations(&self) -> usize {
+        self.derivations.load(Ordering::Relaxed)
+    }
+
+    /// Removes and zeroizes all the cached keys
+    pub fn clear(&self) {
+        self.keys.lock().expect("poisoned KdfCache lock").clear();
+    }
+
+    /// Returns the cached key for `password`, `salt` and `kdf`, deriving and caching it if missing.
+    ///
+    /// The lock is not held while deriving, so concurrent misses on the same entry may derive it more than once.
+    pub(crate) fn derive_key(
+        &self,
+        kdf: &Kdf,
+        password: &[u8],
+        salt: &str,
+    ) -> Result<Zeroizing<Vec<u8>>, CipherError> {
+        let cache_key: CacheKey = (Sha256::digest(password).into(), salt.to_string(), *kdf);
+        if let Some(key) = self
+            .keys
+            .lock()
+            .expect("poisoned KdfCache lock")
+            .get(&cache_key)
+        {
+            return Ok(key.clone());
+        }
+        let key = Zeroizing::new(kdf.derive_key(password, salt)?);
+        self.derivations.fetch_add(1, Ordering::Relaxed);
+        self.keys
+            .lock()
+            .expect("poisoned KdfCache lock")
+            .insert(cache_key, key.clone());
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decrypt::decrypt_with_cache;
+    use crate::encrypt::encrypt;
+
+    #[test]
+    fn test_cache_reuses_keys() {
+        let cache = KdfCache::new();
+        let encrypted = encrypt("password", b"content").unwrap();
+        assert_eq!(
+            decrypt_with_cache("password", &encrypted, &cache)
+                .unwrap()
+                .1,
+            b"content"
+        );
+        assert_eq!(cache.derivations(), 1);
+        assert_eq!(
+            decrypt_with_cache("password", &encrypted, &cache)
+                .unwrap()
+                .1,
+            b"content"
+        );
+        assert_eq!(cache.derivations(), 1);
+
+        // another password or salt is another entry
+        assert!(decrypt_with_cache("wrong", &encrypted, &cache).is_err());
+        assert_eq!(cache.derivations(), 2);
+        let other = encrypt("password", b"other").unwrap();
+        decrypt_with_cache("password", &other, &cache).unwrap();
+        assert_eq!(cache.derivations(), 3);
+
+        cache.clear();
+        decrypt_with_cache("password", &encrypted, &cache).unwrap();
+        assert_eq!(cache.derivations(), 4);
+    }
+
+    #[test]
+    fn test_cache_shared_between_threads() {
+        let cache = KdfCache::new();
+        let encrypted = encrypt("password", b"content").unwrap();
+        decrypt_with_cache("password", &encrypted, &cache).unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    assert_eq!(
+                        decrypt_with_cache("password", &encrypted, &cache)
+                            .unwrap()
+                            .1,
+                        b"content"
+                    );
+                });
+            }
+        });
+        assert_eq!(cache.derivations(), 1);
+    }
+}
@@ -4,10 +4,82 @@
 //!
 //! Read `lib.rs` module documentation for more information.
 
+use crate::algorithm::CipherAlgorithm;
 use pbkdf2::Params;
 
 /// Cipher version
-pub const VERSION: u32 = 0;
+pub const VERSION: u32 = 2;
+
+/// Magic bytes starting the encrypted data, since version 1.
+///
+/// Version 0 data has no magic bytes and starts with its version.
+pub const HEADER_MAGIC: [u8; 4] = *b"MCIH";
+
+/// Magic bytes ending the encrypted data, since version 1.
+///
+/// A missing footer on data starting with `HEADER_MAGIC` means that it was truncated.
+pub const FOOTER_MAGIC: [u8; 4] = *b"MCIF";
+
+/// Magic bytes starting multi-recipient encrypted data.
+pub const MULTI_MAGIC: [u8; 4] = *b"MCIM";
+
+/// Version of the multi-recipient format, independent of `VERSION`.
+pub const MULTI_VERSION: u32 = 1;
+
+/// Maximum number of recipients of multi-recipient encrypted data.
+///
+/// Finding the wrapped key of a password derives a key per recipient,
+/// so the count read from untrusted data must be bounded.
+pub const MAX_RECIPIENTS: u32 = 16;
+
+/// Header flag set when a content fingerprint follows the flags, since version 2.
+pub const FLAG_FINGERPRINT: u8 = 0b0000_0001;
+
+/// Header flag set when the key is derived with scrypt, whose parameters follow the flags
+/// (after the content fingerprint if any), since version 2.
+pub const FLAG_SCRYPT: u8 = 0b0000_0010;
+
+/// Header flag set when a metadata section follows the flags (after the scrypt parameters if any), since version 2.
+///
+/// The whole header is then authenticated as associated data of the encryption,
+/// so that the metadata can be read without the password but not modified undetected.
+pub const FLAG_METADATA: u8 = 0b0000_0100;
+
+/// All the header flags known by this version.
+pub const KNOWN_FLAGS: u8 = FLAG_FINGERPRINT | FLAG_SCRYPT | FLAG_METADATA;
+
+/// Maximum size in bytes of the label of the metadata section.
+pub const MAX_LABEL_SIZE: u32 = 256;
+
+/// Maximum base 2 logarithm of the scrypt CPU/memory cost accepted in a header or for an encryption.
+///
+/// With `MAX_SCRYPT_R` and `MAX_SCRYPT_P`, a derivation needs at most 128 MiB of memory
+/// (`128 * r * 2^log_n` bytes), so that a crafted header cannot exhaust the memory of the decrypting node.
+pub const MAX_SCRYPT_LOG_N: u8 = 17;
+
+/// Maximum scrypt block size accepted in a header or for an encryption.
+pub const MAX_SCRYPT_R: u32 = 8;
+
+/// Maximum scrypt parallelization accepted in a header or for an encryption.
+pub const MAX_SCRYPT_P: u32 = 1;
+
+/// Size of the identifier of the key a content fingerprint was computed with.
+pub const FINGERPRINT_KEY_ID_SIZE: usize = 8;
+
+/// Size of a content fingerprint (HMAC-SHA256 output).
+pub const FINGERPRINT_SIZE: usize = 32;
+
+/// `PBKDF2` salt of the fingerprint key.
+///
+/// Unlike the encryption salt it is fixed, so that a password always derives the same fingerprint key
+/// and the fingerprints of two encryptions of the same plaintext are equal.
+/// Fingerprints are only stored masked with the per-file cipher key, so this does not make them public.
+pub const FINGERPRINT_SALT: &str = "massafingerprint";
+
+/// Algorithm the data is encrypted with.
+///
+/// Sizes that depend on the algorithm, like the authentication tag size, must be read from it.
+pub const ALGORITHM: CipherAlgorithm = CipherAlgorithm::Aes256Gcm;
 
 /// AES-GCM-SIV nonce size.
 ///
@@ -23,3 +95,6 @@ pub const HASH_PARAMS: Params = Params {
     rounds: 10_000,
     output_length: 32,
 };
+
+/// Prefix of the textual form of encrypted data, followed by its URL-safe base64 encoding.
+pub const TEXT_PREFIX: &str = "massacipher:";
@@ -4,47 +4,186 @@
 //!
 //! Read `lib.rs` module documentation for more information.
 
-use aes_gcm::aead::Aead;
-use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
-use pbkdf2::{
-    password_hash::{PasswordHasher, SaltString},
-    Pbkdf2,
-};
-
-use crate::constants::{HASH_PARAMS, NONCE_SIZE, SALT_SIZE};
-use crate::error::CipherError;
-use massa_serialization::{DeserializeError, Deserializer, U32VarIntDeserializer};
+use aes_gcm::aead::consts::U12;
+use aes_gcm::aead::{Aead, AeadInPlace, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce, Tag};
+use zeroize::{Zeroize, Zeroizing};
 
-use std::ops::Bound::Included;
+use crate::cache::KdfCache;
+use crate::constants::{ALGORITHM, NONCE_SIZE, SALT_SIZE};
+use crate::error::CipherError;
+use crate::header::read_envelope;
 
 /// Decryption function using AES-GCM cipher.
 ///
 /// Read `lib.rs` module documentation for more information.
 pub fn decrypt(password: &str, data: &[u8]) -> Result<(u32, Vec<u8>), CipherError> {
-    // parse cipher version
-    let (rest, version) = U32VarIntDeserializer::new(Included(0), Included(u32::MAX))
-        .deserialize::<DeserializeError>(data)
+    decrypt_bytes(password.as_bytes(), data)
+}
+
+/// Decryption function using AES-GCM cipher, with a password given as raw bytes
+/// that do not need to be valid UTF-8.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn decrypt_bytes(password: &[u8], data: &[u8]) -> Result<(u32, Vec<u8>), CipherError> {
+    let opened = open(password, data, None, 0)?;
+
+    // decrypt the data
+    let decrypted_bytes = opened.decrypt()?;
+    Ok((opened.version, decrypted_bytes))
+}
+
+/// Options of `decrypt_with_config`
+#[derive(Debug, Clone, Default)]
+pub struct DecryptConfig {
+    /// Data of an older version is refused with `CipherError::VersionTooOld`,
+    /// to force its migration to a newer version
+    pub min_accepted_version: u32,
+}
+
+/// Decryption function using AES-GCM cipher, applying the options of `config`.
+///
+/// The version is checked before deriving the cipher key.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn decrypt_with_config(
+    password: &str,
+    data: &[u8],
+    config: &DecryptConfig,
+) -> Result<(u32, Vec<u8>), CipherError> {
+    let opened = open(password.as_bytes(), data, None, config.min_accepted_version)?;
+    let decrypted_bytes = opened.decrypt()?;
+    Ok((opened.version, decrypted_bytes))
+}
+
+/// Decryption function using AES-GCM cipher, reusing the cipher keys already derived in `cache`
+/// and caching the newly derived ones.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn decrypt_with_cache(
+    password: &str,
+    data: &[u8],
+    cache: &KdfCache,
+) -> Result<(u32, Vec<u8>), CipherError> {
+    let opened = open(password.as_bytes(), data, Some(cache), 0)?;
+    let decrypted_bytes = opened.decrypt()?;
+    Ok((opened.version, decrypted_bytes))
+}
+
+/// Decryption function using AES-GCM cipher that decrypts `data` in place, without allocating.
+///
+/// On success, the encrypted bytes are overwritten by the plaintext,
+/// and the returned slice borrows that plaintext within `data`.
+/// On failure, `data` is left unchanged: the authentication tag is checked before anything
+/// is decrypted in place, which requires `aes-gcm` 0.10.3 or later (RUSTSEC-2023-0096).
+pub fn decrypt_borrowed<'a>(password: &str, data: &'a mut [u8]) -> Result<&'a [u8], CipherError> {
+    let (cipher, nonce, aad_len, encrypted_range) = {
+        let opened = open(password.as_bytes(), data, None, 0)?;
+        // `encrypted_bytes` is a subslice of `data`, and `aad` is empty or a prefix of `data`
+        let start = opened.encrypted_bytes.as_ptr() as usize - data.as_ptr() as usize;
+        (
+            opened.cipher,
+            *opened.nonce,
+            opened.aad.len(),
+            start..start + opened.encrypted_bytes.len(),
+        )
+    };
+    let (head, tail) = data.split_at_mut(encrypted_range.start);
+    let encrypted_bytes = &mut tail[..encrypted_range.len()];
+    let (plaintext, tag) =
+        encrypted_bytes.split_at_mut(encrypted_bytes.len() - ALGORITHM.tag_len());
+    cipher
+        .decrypt_in_place_detached(&nonce, &head[..aad_len], plaintext, Tag::from_slice(tag))
         .map_err(|_| {
-            CipherError::DecryptionError(
-                "wallet file truncated: version missing or incomplete".to_string(),
-            )
+            CipherError::DecryptionError("wrong password or corrupted data".to_string())
         })?;
+    Ok(plaintext)
+}
+
+/// Checks whether `password` decrypts `data`, without returning the plaintext.
+///
+/// The data is decrypted in a scratch buffer that is zeroized right after the authentication tag is verified.
+/// Returns `Ok(false)` for a wrong password or corrupted data, and an error if `data` is malformed or truncated.
+pub fn verify_password(password: &str, data: &[u8]) -> Result<bool, CipherError> {
+    let opened = open(password.as_bytes(), data, None, 0)?;
+    let mut scratch = opened.encrypted_bytes.to_vec();
+    let verified = opened
+        .cipher
+        .decrypt_in_place(opened.nonce, opened.aad, &mut scratch)
+        .is_ok();
+    scratch.zeroize();
+    Ok(verified)
+}
+
+/// Encrypted data ready to be decrypted
+struct Opened<'a> {
+    /// cipher version
+    version: u32,
+    /// cipher derived from the password
+    cipher: Aes256Gcm,
+    /// AES-GCM nonce
+    nonce: &'a Nonce<U12>,
+    /// associated data authenticated along with the encrypted bytes
+    aad: &'a [u8],
+    /// encrypted bytes, ending with the authentication tag
+    encrypted_bytes: &'a [u8],
+}
 
-    // parse PBKDF2 salt
+impl Opened<'_> {
+    /// Decrypts the encrypted bytes and checks their authentication tag
+    fn decrypt(&self) -> Result<Vec<u8>, CipherError> {
+        self.cipher
+            .decrypt(
+                self.nonce,
+                Payload {
+                    msg: self.encrypted_bytes,
+                    aad: self.aad,
+                },
+            )
+            .map_err(|_| {
+                CipherError::DecryptionError("wrong password or corrupted data".to_string())
+            })
+    }
+}
+
+/// Parses the framing, salt and nonce of encrypted data and derives the cipher from the password,
+/// through `cache` if any.
+///
+/// Data of a version below `min_accepted_version` is refused.
+fn open<'a>(
+    password: &[u8],
+    data: &'a [u8],
+    cache: Option<&KdfCache>,
+    min_accepted_version: u32,
+) -> Result<Opened<'a>, CipherError> {
+    // parse cipher version and framing
+    let envelope = read_envelope(data)?;
+    if envelope.version < min_accepted_version {
+        return Err(CipherError::VersionTooOld(
+            envelope.version,
+            min_accepted_version,
+        ));
+    }
+    if envelope.version >= 1 && !envelope.has_footer {
+        return Err(CipherError::DecryptionError(
+            "wallet file truncated: footer missing".to_string(),
+        ));
+    }
+    let (version, rest) = (envelope.version, envelope.content);
+
+    // parse salt
     let salt_data = rest.get(..SALT_SIZE).ok_or_else(|| {
         CipherError::DecryptionError(
             "wallet file truncated: salt missing or incomplete".to_string(),
         )
     })?;
-    let salt = SaltString::new(std::str::from_utf8(salt_data)?)
-        .map_err(|e| CipherError::DecryptionError(e.to_string()))?;
 
-    // compute PBKDF2 password hash
-    let password_hash = Pbkdf2
-        .hash_password_customized(password.as_bytes(), None, None, HASH_PARAMS, &salt)
-        .map_err(|e| CipherError::DecryptionError(e.to_string()))?
-        .hash
-        .expect("content is missing after a successful hash");
+    // derive the cipher key
+    let salt = std::str::from_utf8(salt_data)?;
+    let key = match cache {
+        Some(cache) => cache.derive_key(&envelope.kdf, password, salt)?,
+        None => Zeroizing::new(envelope.kdf.derive_key(password, salt)?),
+    };
 
     // parse AES-GCM nonce
     let nonce_end_index = SALT_SIZE + NONCE_SIZE;
@@ -54,19 +193,206 @@ pub fn decrypt(password: &str, data: &[u8]) -> Result<(u32, Vec<u8>), CipherErro
         )
     })?);
 
-    // decrypt the data
-    let cipher = Aes256Gcm::new_from_slice(password_hash.as_bytes()).expect("invalid size key");
-    let decrypted_bytes = cipher
-        .decrypt(
-            nonce,
-            rest.get(nonce_end_index..).ok_or_else(|| {
-                CipherError::DecryptionError(
-                    "wallet file truncated: encrypted data missing or incomplete".to_string(),
-                )
-            })?,
-        )
-        .map_err(|_| {
-            CipherError::DecryptionError("wrong password or corrupted data".to_string())
+    // parse encrypted data
+    let encrypted_bytes = rest
+        .get(nonce_end_index..)
+        .filter(|encrypted| encrypted.len() >= ALGORITHM.tag_len())
+        .ok_or_else(|| {
+            CipherError::DecryptionError(
+                "wallet file truncated: encrypted data missing or incomplete".to_string(),
+            )
         })?;
-    Ok((version, decrypted_bytes))
+
+    let cipher = Aes256Gcm::new_from_slice(&key).expect("invalid size key");
+    Ok(Opened {
+        version,
+        cipher,
+        nonce,
+        aad: envelope.aad,
+        encrypted_bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{
+        FOOTER_MAGIC, HEADER_MAGIC, MAX_SCRYPT_LOG_N, MAX_SCRYPT_P, MAX_SCRYPT_R,
+    };
+    use crate::encrypt::{encrypt, encrypt_bytes, encrypt_with_kdf, encrypt_with_metadata};
+    use crate::header::{inspect, Metadata};
+    use crate::kdf::Kdf;
+
+    #[test]
+    fn test_decrypt_metadata_tampered() {
+        let metadata = Metadata {
+            timestamp: 42,
+            label: "wallet".to_string(),
+        };
+        let encrypted = encrypt_with_metadata("password", b"content", &metadata).unwrap();
+        let (_, decrypted) = decrypt("password", &encrypted).unwrap();
+        assert_eq!(decrypted, b"content");
+        let mut data = encrypted.clone();
+        assert_eq!(decrypt_borrowed("password", &mut data).unwrap(), b"content");
+        assert!(verify_password("password", &encrypted).unwrap());
+
+        // the single byte varint timestamp follows the magic, the version and the flags
+        let mut tampered = encrypted;
+        tampered[HEADER_MAGIC.len() + 2] ^= 1;
+        assert_eq!(inspect(&tampered).unwrap().metadata.unwrap().timestamp, 43);
+        assert!(decrypt("password", &tampered).is_err());
+        assert!(!verify_password("password", &tampered).unwrap());
+        assert!(decrypt_borrowed("password", &mut tampered).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_borrowed() {
+        let encrypted = encrypt("password", b"content").unwrap();
+        let (_, expected) = decrypt("password", &encrypted).unwrap();
+
+        let mut data = encrypted.clone();
+        assert!(decrypt_borrowed("wrong", &mut data).is_err());
+        assert_eq!(data, encrypted);
+        assert_eq!(decrypt_borrowed("password", &mut data).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_decrypt_version_0() {
+        // version 0 data has no header magic, flags nor footer
+        let encrypted = encrypt("password", b"content").unwrap();
+        let mut legacy = vec![0];
+        // skip the single byte varint version and the flags
+        legacy.extend(&encrypted[HEADER_MAGIC.len() + 2..encrypted.len() - FOOTER_MAGIC.len()]);
+
+        let (version, decrypted) = decrypt("password", &legacy).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(decrypted, b"content");
+    }
+
+    #[test]
+    fn test_decrypt_min_accepted_version() {
+        // version 1 data has no flags
+        let encrypted = encrypt("password", b"content").unwrap();
+        let mut legacy = HEADER_MAGIC.to_vec();
+        legacy.push(1);
+        legacy.extend(&encrypted[HEADER_MAGIC.len() + 2..]);
+
+        let config = DecryptConfig {
+            min_accepted_version: 1,
+        };
+        let (version, decrypted) = decrypt_with_config("password", &legacy, &config).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(decrypted, b"content");
+
+        let config = DecryptConfig {
+            min_accepted_version: 2,
+        };
+        assert!(matches!(
+            decrypt_with_config("password", &legacy, &config),
+            Err(CipherError::VersionTooOld(1, 2))
+        ));
+        assert!(decrypt_with_config("password", &encrypted, &config).is_ok());
+    }
+
+    #[test]
+    fn test_decrypt_bytes_non_utf8_password() {
+        let password = [0xff, 0xfe, 0x00, 0xc3, 0x28];
+        assert!(std::str::from_utf8(&password).is_err());
+        let encrypted = encrypt_bytes(&password, b"content").unwrap();
+
+        let (_, decrypted) = decrypt_bytes(&password, &encrypted).unwrap();
+        assert_eq!(decrypted, b"content");
+        assert!(decrypt_bytes(&password[..4], &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_str_matches_bytes() {
+        let encrypted = encrypt("password", b"content").unwrap();
+        let (_, decrypted) = decrypt_bytes(b"password", &encrypted).unwrap();
+        assert_eq!(decrypted, b"content");
+    }
+
+    #[test]
+    fn test_decrypt_truncated_tag() {
+        // the encryption of an empty plaintext is made of the authentication tag only
+        let encrypted = encrypt("password", b"").unwrap();
+        let footer_start = encrypted.len() - FOOTER_MAGIC.len();
+        let mut truncated = encrypted[..footer_start - 1].to_vec();
+        truncated.extend(FOOTER_MAGIC);
+
+        let err = decrypt("password", &truncated).unwrap_err().to_string();
+        assert!(
+            err.contains("encrypted data missing or incomplete"),
+            "{}",
+            err
+        );
+        let mut corrupted = encrypted;
+        corrupted[footer_start - ALGORITHM.tag_len()] ^= 1;
+        let err = decrypt("password", &corrupted).unwrap_err().to_string();
+        assert!(err.contains("wrong password or corrupted data"), "{}", err);
+    }
+
+    #[test]
+    fn test_verify_password() {
+        let encrypted = encrypt("password", b"content").unwrap();
+        assert!(verify_password("password", &encrypted).unwrap());
+        assert!(!verify_password("wrong password", &encrypted).unwrap());
+        assert!(verify_password("password", &encrypted[..encrypted.len() - 10]).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_scrypt() {
+        let kdf = Kdf::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        };
+        let encrypted = encrypt_with_kdf("password", b"content", kdf).unwrap();
+        let (_, decrypted) = decrypt("password", &encrypted).unwrap();
+        assert_eq!(decrypted, b"content");
+        assert!(decrypt("wrong password", &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_scrypt_limits() {
+        // parameters above the maximum are refused for an encryption
+        for kdf in [
+            Kdf::Scrypt {
+                log_n: MAX_SCRYPT_LOG_N + 1,
+                r: 8,
+                p: 1,
+            },
+            Kdf::Scrypt {
+                log_n: 10,
+                r: MAX_SCRYPT_R + 1,
+                p: 1,
+            },
+            Kdf::Scrypt {
+                log_n: 10,
+                r: 8,
+                p: MAX_SCRYPT_P + 1,
+            },
+        ] {
+            assert!(matches!(
+                encrypt_with_kdf("password", b"content", kdf),
+                Err(CipherError::KdfError(_))
+            ));
+        }
+
+        // and in a header, before any derivation
+        let kdf = Kdf::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        };
+        let mut encrypted = encrypt_with_kdf("password", b"content", kdf).unwrap();
+        // magic, one byte version and flags, then log_n
+        let log_n_index = HEADER_MAGIC.len() + 2;
+        assert_eq!(encrypted[log_n_index], 10);
+        encrypted[log_n_index] = MAX_SCRYPT_LOG_N + 1;
+        assert!(matches!(
+            decrypt("password", &encrypted),
+            Err(CipherError::InvalidFormat(_))
+        ));
+    }
 }
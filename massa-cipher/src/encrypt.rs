@@ -4,34 +4,79 @@
 //!
 //! Read `lib.rs` module documentation for more information.
 
-use aes_gcm::aead::Aead;
+use aes_gcm::aead::{Aead, Payload};
 use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
-use pbkdf2::password_hash::Salt;
-use pbkdf2::{password_hash::PasswordHasher, Pbkdf2};
 use rand::{distributions::Alphanumeric, thread_rng, Rng, RngCore};
+use zeroize::Zeroizing;
 
-use crate::constants::{HASH_PARAMS, NONCE_SIZE, SALT_SIZE, VERSION};
+use crate::constants::{FINGERPRINT_SIZE, FOOTER_MAGIC, NONCE_SIZE, SALT_SIZE};
 use crate::error::CipherError;
-use massa_serialization::{Serializer, U32VarIntSerializer};
+use crate::fingerprint::Fingerprint;
+use crate::header::{write_header, Metadata};
+use crate::kdf::Kdf;
 
 /// Encryption function using AES-GCM cipher.
 ///
 /// Read `lib.rs` module documentation for more information.
 pub fn encrypt(password: &str, data: &[u8]) -> Result<Vec<u8>, CipherError> {
-    // generate the PBKDF2 salt
+    encrypt_bytes(password.as_bytes(), data)
+}
+
+/// Encryption function using AES-GCM cipher, with a password given as raw bytes
+/// that do not need to be valid UTF-8.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn encrypt_bytes(password: &[u8], data: &[u8]) -> Result<Vec<u8>, CipherError> {
+    encrypt_framed(password, data, Kdf::Pbkdf2, None, None)
+}
+
+/// Encryption function using AES-GCM cipher, with the cipher key derived by `kdf`.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn encrypt_with_kdf(password: &str, data: &[u8], kdf: Kdf) -> Result<Vec<u8>, CipherError> {
+    encrypt_framed(password.as_bytes(), data, kdf, None, None)
+}
+
+/// Encryption function using AES-GCM cipher, storing `metadata` in the header.
+///
+/// The metadata can be read with `inspect` without the password, and is authenticated on decryption.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn encrypt_with_metadata(
+    password: &str,
+    data: &[u8],
+    metadata: &Metadata,
+) -> Result<Vec<u8>, CipherError> {
+    encrypt_framed(password.as_bytes(), data, Kdf::Pbkdf2, None, Some(metadata))
+}
+
+/// Encrypts `data` and frames it with a header recording the key derivation function
+/// and holding the optional content fingerprint, sealed with the cipher key, and metadata
+pub(crate) fn encrypt_framed(
+    password: &[u8],
+    data: &[u8],
+    kdf: Kdf,
+    fingerprint: Option<&[u8; FINGERPRINT_SIZE]>,
+    metadata: Option<&Metadata>,
+) -> Result<Vec<u8>, CipherError> {
+    // generate the salt
     let raw_salt: String = thread_rng()
         .sample_iter(&Alphanumeric)
         .take(SALT_SIZE)
         .map(char::from)
         .collect();
-    let salt = Salt::new(&raw_salt).expect("salt creation failed");
 
-    // compute PBKDF2 password hash
-    let password_hash = Pbkdf2
-        .hash_password_customized(password.as_bytes(), None, None, HASH_PARAMS, salt)
-        .map_err(|e| CipherError::EncryptionError(e.to_string()))?
-        .hash
-        .expect("content is missing after a successful hash");
+    // derive the cipher key
+    let key = Zeroizing::new(kdf.derive_key(password, &raw_salt)?);
+
+    // write the header: with metadata, it is authenticated as associated data
+    let fingerprint = fingerprint.map(|fingerprint| Fingerprint::seal(fingerprint, &key));
+    let mut content = Vec::new();
+    write_header(&mut content, kdf, fingerprint.as_ref(), metadata)?;
+    let aad: &[u8] = match metadata {
+        Some(_) => &content,
+        None => &[],
+    };
 
     // generate the AES-GCM nonce
     let mut nonce_bytes = [0u8; NONCE_SIZE];
@@ -39,18 +84,15 @@ pub fn encrypt(password: &str, data: &[u8]) -> Result<Vec<u8>, CipherError> {
     let nonce = Nonce::from_slice(&nonce_bytes);
 
     // encrypt the data
-    let cipher = Aes256Gcm::new_from_slice(password_hash.as_bytes()).expect("invalid key length");
+    let cipher = Aes256Gcm::new_from_slice(&key).expect("invalid key length");
     let encrypted_bytes = cipher
-        .encrypt(nonce, data.as_ref())
+        .encrypt(nonce, Payload { msg: data, aad })
         .map_err(|e| CipherError::EncryptionError(e.to_string()))?;
 
     // build the encryption result
-    let mut content = Vec::new();
-    U32VarIntSerializer::new()
-        .serialize(&VERSION, &mut content)
-        .map_err(|err| CipherError::EncryptionError(err.to_string()))?;
-    content.extend(salt.as_bytes());
+    content.extend(raw_salt.as_bytes());
     content.extend(nonce_bytes);
     content.extend(encrypted_bytes);
+    content.extend(FOOTER_MAGIC);
     Ok(content)
 }
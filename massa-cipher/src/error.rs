@@ -12,6 +12,14 @@ pub enum CipherError {
     EncryptionError(String),
     /// Decryption error: {0}
     DecryptionError(String),
+    /// Key derivation error: {0}
+    KdfError(String),
+    /// Invalid format: {0}
+    InvalidFormat(String),
+    /// Version {0} is older than the minimum accepted version {1}: migrate the data to a newer version
+    VersionTooOld(u32, u32),
+    /// Signature error: {0}
+    SignatureError(#[from] massa_signature::MassaSignatureError),
     /// `Utf8` error: {0}
     Utf8Error(#[from] std::str::Utf8Error),
 }
@@ -0,0 +1,197 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! massa-cipher fingerprint module.
+//!
+//! A content fingerprint is an HMAC-SHA256 of the plaintext, keyed by a fingerprint key derived
+//! from the password, so that two encryptions of the same plaintext under the same password
+//! share it and can be compared without decrypting them.
+//!
+//! The fingerprint is not stored as is: it is masked with a value derived from the cipher key
+//! of the file, which depends on its random salt, and preceded by a key check derived the same way.
+//! Nothing in the header can thus be checked against a password guess without a full key derivation
+//! for this very file, and equal fingerprints are not visible to whoever does not know the password.
+//!
+//! Read `lib.rs` module documentation for more information.
+
+use hmac::{Hmac, Mac};
+use pbkdf2::password_hash::{PasswordHasher, Salt};
+use pbkdf2::Pbkdf2;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::constants::{
+    FINGERPRINT_KEY_ID_SIZE, FINGERPRINT_SALT, FINGERPRINT_SIZE, HASH_PARAMS, SALT_SIZE,
+};
+use crate::encrypt::encrypt_framed;
+use crate::error::CipherError;
+use crate::header::read_envelope;
+use crate::kdf::Kdf;
+
+/// Content fingerprint stored in the header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Fingerprint {
+    /// derived from the cipher key of the file, so that fingerprints sealed with another password are not compared
+    pub key_id: [u8; FINGERPRINT_KEY_ID_SIZE],
+    /// HMAC of the plaintext, masked with a value derived from the cipher key of the file
+    pub value: [u8; FINGERPRINT_SIZE],
+}
+
+impl Fingerprint {
+    /// Computes the unmasked fingerprint of `data` with the fingerprint key derived from `password`
+    pub(crate) fn compute(
+        password: &str,
+        data: &[u8],
+    ) -> Result<[u8; FINGERPRINT_SIZE], CipherError> {
+        let key = fingerprint_key(password)?;
+        Ok(hmac_sha256(&key, data))
+    }
+
+    /// Masks an unmasked fingerprint with the cipher key of the file it is stored in
+    pub(crate) fn seal(value: &[u8; FINGERPRINT_SIZE], cipher_key: &[u8]) -> Self {
+        let mask = hmac_sha256(cipher_key, b"massa fingerprint mask");
+        Fingerprint {
+            key_id: key_id(cipher_key),
+            value: std::array::from_fn(|index| value[index] ^ mask[index]),
+        }
+    }
+
+    /// Unmasks the fingerprint with the cipher key of the file,
+    /// or returns `None` if it was sealed with another key
+    fn open(&self, cipher_key: &[u8]) -> Option<[u8; FINGERPRINT_SIZE]> {
+        if self.key_id != key_id(cipher_key) {
+            return None;
+        }
+        let mask = hmac_sha256(cipher_key, b"massa fingerprint mask");
+        Some(std::array::from_fn(|index| self.value[index] ^ mask[index]))
+    }
+}
+
+/// Derives the fingerprint key from the password with `PBKDF2` and the fixed fingerprint salt.
+///
+/// The key is the same for every file encrypted with the password, but the fingerprints
+/// it computes are only stored masked, see the module documentation.
+fn fingerprint_key(password: &str) -> Result<Zeroizing<Vec<u8>>, CipherError> {
+    let salt = Salt::new(FINGERPRINT_SALT).expect("salt creation failed");
+    let password_hash = Pbkdf2
+        .hash_password_customized(password.as_bytes(), None, None, HASH_PARAMS, salt)
+        .map_err(|e| CipherError::EncryptionError(e.to_string()))?
+        .hash
+        .expect("content is missing after a successful hash");
+    Ok(Zeroizing::new(password_hash.as_bytes().to_vec()))
+}
+
+/// Key check of a file cipher key, which does not reveal the key itself
+fn key_id(cipher_key: &[u8]) -> [u8; FINGERPRINT_KEY_ID_SIZE] {
+    let mut id = [0u8; FINGERPRINT_KEY_ID_SIZE];
+    id.copy_from_slice(
+        &hmac_sha256(cipher_key, b"massa fingerprint key id")[..FINGERPRINT_KEY_ID_SIZE],
+    );
+    id
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; FINGERPRINT_SIZE] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    let mut value = [0u8; FINGERPRINT_SIZE];
+    value.copy_from_slice(&mac.finalize().into_bytes());
+    value
+}
+
+/// Encryption function using AES-GCM cipher, which also stores a fingerprint of `data` in the header.
+///
+/// The fingerprint reveals whether two encryptions under the same password hold the same plaintext,
+/// but nothing else about the plaintext.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn encrypt_with_fingerprint(password: &str, data: &[u8]) -> Result<Vec<u8>, CipherError> {
+    let fingerprint = Zeroizing::new(Fingerprint::compute(password, data)?);
+    encrypt_framed(
+        password.as_bytes(),
+        data,
+        Kdf::Pbkdf2,
+        Some(&*fingerprint),
+        None,
+    )
+}
+
+/// Tells whether two encrypted data hold the same plaintext by comparing their content fingerprints,
+/// without decrypting them.
+///
+/// Both must have been encrypted with `encrypt_with_fingerprint` under `password`.
+/// Unmasking a fingerprint costs a key derivation, like a decryption.
+/// The fingerprints are not authenticated by the encryption: data tampered with by someone
+/// knowing the password can lie about its content.
+pub fn same_plaintext(a: &[u8], b: &[u8], password: &str) -> Result<bool, CipherError> {
+    let fingerprint_of = |data: &[u8]| -> Result<Zeroizing<[u8; FINGERPRINT_SIZE]>, CipherError> {
+        let envelope = read_envelope(data)?;
+        let fingerprint = envelope.fingerprint.ok_or_else(|| {
+            CipherError::InvalidFormat("no content fingerprint in the header".to_string())
+        })?;
+        let salt = envelope.content.get(..SALT_SIZE).ok_or_else(|| {
+            CipherError::DecryptionError(
+                "wallet file truncated: salt missing or incomplete".to_string(),
+            )
+        })?;
+        let cipher_key = Zeroizing::new(
+            envelope
+                .kdf
+                .derive_key(password.as_bytes(), std::str::from_utf8(salt)?)?,
+        );
+        let value = fingerprint.open(&cipher_key).ok_or_else(|| {
+            CipherError::DecryptionError(
+                "content fingerprint computed with another password".to_string(),
+            )
+        })?;
+        Ok(Zeroizing::new(value))
+    };
+    Ok(*fingerprint_of(a)? == *fingerprint_of(b)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decrypt::decrypt;
+    use crate::encrypt::encrypt;
+
+    #[test]
+    fn test_same_plaintext_identical() {
+        let a = encrypt_with_fingerprint("password", b"content").unwrap();
+        let b = encrypt_with_fingerprint("password", b"content").unwrap();
+        assert_ne!(a, b);
+        assert!(same_plaintext(&a, &b, "password").unwrap());
+        assert_eq!(decrypt("password", &a).unwrap().1, b"content");
+    }
+
+    #[test]
+    fn test_same_plaintext_differing() {
+        let a = encrypt_with_fingerprint("password", b"content").unwrap();
+        let b = encrypt_with_fingerprint("password", b"other content").unwrap();
+        assert!(!same_plaintext(&a, &b, "password").unwrap());
+    }
+
+    #[test]
+    fn test_fingerprint_not_linkable() {
+        // without the password, the stored sections of equal plaintexts and passwords differ
+        let a = encrypt_with_fingerprint("password", b"content").unwrap();
+        let b = encrypt_with_fingerprint("password", b"content").unwrap();
+        let fingerprint_a = read_envelope(&a).unwrap().fingerprint.unwrap();
+        let fingerprint_b = read_envelope(&b).unwrap().fingerprint.unwrap();
+        assert_ne!(fingerprint_a.key_id, fingerprint_b.key_id);
+        assert_ne!(fingerprint_a.value, fingerprint_b.value);
+    }
+
+    #[test]
+    fn test_same_plaintext_errors() {
+        let a = encrypt_with_fingerprint("password", b"content").unwrap();
+        let b = encrypt_with_fingerprint("other password", b"content").unwrap();
+        let c = encrypt("password", b"content").unwrap();
+        assert!(matches!(
+            same_plaintext(&a, &b, "password"),
+            Err(CipherError::DecryptionError(_))
+        ));
+        assert!(matches!(
+            same_plaintext(&a, &c, "password"),
+            Err(CipherError::InvalidFormat(_))
+        ));
+    }
+}
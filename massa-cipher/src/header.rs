@@ -0,0 +1,417 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! massa-cipher header module.
+//!
+//! Recognizes the framing of encrypted data: the header magic, version and flags at its start,
+//! and the footer magic at its end.
+//!
+//! Read `lib.rs` module documentation for more information.
+
+use crate::constants::{
+    ALGORITHM, FINGERPRINT_KEY_ID_SIZE, FINGERPRINT_SIZE, FLAG_FINGERPRINT, FLAG_METADATA,
+    FLAG_SCRYPT, FOOTER_MAGIC, HEADER_MAGIC, KNOWN_FLAGS, MAX_LABEL_SIZE, MAX_SCRYPT_LOG_N,
+    MAX_SCRYPT_P, MAX_SCRYPT_R, VERSION,
+};
+use crate::error::CipherError;
+use crate::fingerprint::Fingerprint;
+use crate::kdf::Kdf;
+use massa_serialization::{
+    DeserializeError, Deserializer, Serializer, U32VarIntDeserializer, U32VarIntSerializer,
+    U64VarIntDeserializer, U64VarIntSerializer,
+};
+
+use std::ops::Bound::Included;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Encrypted data split according to its framing
+pub(crate) struct Envelope<'a> {
+    /// cipher version
+    pub version: u32,
+    /// content between the header and the footer
+    pub content: &'a [u8],
+    /// true if the footer magic was found
+    pub has_footer: bool,
+    /// content fingerprint, since version 2
+    pub fingerprint: Option<Fingerprint>,
+    /// key derivation function
+    pub kdf: Kdf,
+    /// metadata, since version 2
+    pub metadata: Option<Metadata>,
+    /// associated data authenticated along with the content: the whole header if it holds metadata
+    pub aad: &'a [u8],
+}
+
+/// Metadata stored in the header, readable without the password and authenticated on decryption
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    /// creation or last modification time, in seconds since the unix epoch
+    pub timestamp: u64,
+    /// free-form label, of at most `MAX_LABEL_SIZE` bytes
+    pub label: String,
+}
+
+impl Metadata {
+    /// Creates metadata with the current time and `label`
+    pub fn now(label: &str) -> Self {
+        Metadata {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            label: label.to_string(),
+        }
+    }
+}
+
+/// Information that can be read from encrypted data without the password
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CipherInfo {
+    /// cipher version
+    pub version: u32,
+    /// true if the footer magic is present
+    pub has_footer: bool,
+    /// true if the header holds a content fingerprint
+    pub has_fingerprint: bool,
+    /// key derivation function
+    pub kdf: Kdf,
+    /// metadata, not authenticated until the data is decrypted
+    pub metadata: Option<Metadata>,
+}
+
+impl CipherInfo {
+    /// Returns true if the data starts like a complete file of a version with a footer, but the footer is missing
+    pub fn is_truncated(&self) -> bool {
+        self.version >= 1 && !self.has_footer
+    }
+}
+
+/// Writes the header magic, the current version, its flags and the sections they announce
+pub(crate) fn write_header(
+    buffer: &mut Vec<u8>,
+    kdf: Kdf,
+    fingerprint: Option<&Fingerprint>,
+    metadata: Option<&Metadata>,
+) -> Result<(), CipherError> {
+    let u32_serializer = U32VarIntSerializer::new();
+    buffer.extend(HEADER_MAGIC);
+    u32_serializer
+        .serialize(&VERSION, buffer)
+        .map_err(|err| CipherError::EncryptionError(err.to_string()))?;
+
+    // flags
+    let mut flags = 0;
+    if fingerprint.is_some() {
+        flags |= FLAG_FINGERPRINT;
+    }
+    if let Kdf::Scrypt { .. } = kdf {
+        flags |= FLAG_SCRYPT;
+    }
+    if metadata.is_some() {
+        flags |= FLAG_METADATA;
+    }
+    buffer.push(flags);
+
+    // content fingerprint
+    if let Some(fingerprint) = fingerprint {
+        buffer.extend(fingerprint.key_id);
+        buffer.extend(fingerprint.value);
+    }
+
+    // scrypt parameters
+    if let Kdf::Scrypt { log_n, r, p } = kdf {
+        buffer.push(log_n);
+        for param in [r, p] {
+            u32_serializer
+                .serialize(&param, buffer)
+                .map_err(|err| CipherError::EncryptionError(err.to_string()))?;
+        }
+    }
+
+    // metadata
+    if let Some(metadata) = metadata {
+        let label_size = u32::try_from(metadata.label.len())
+            .ok()
+            .filter(|size| *size <= MAX_LABEL_SIZE)
+            .ok_or_else(|| {
+                CipherError::EncryptionError(format!(
+                    "metadata label above the maximum of {} bytes",
+                    MAX_LABEL_SIZE
+                ))
+            })?;
+        U64VarIntSerializer::new()
+            .serialize(&metadata.timestamp, buffer)
+            .map_err(|err| CipherError::EncryptionError(err.to_string()))?;
+        u32_serializer
+            .serialize(&label_size, buffer)
+            .map_err(|err| CipherError::EncryptionError(err.to_string()))?;
+        buffer.extend(metadata.label.as_bytes());
+    }
+    Ok(())
+}
+
+/// Reads the flags of a version 2 header, and the optional sections they announce
+#[allow(clippy::type_complexity)]
+fn read_flags(
+    data: &[u8],
+) -> Result<(&[u8], Option<Fingerprint>, Kdf, Option<Metadata>), CipherError> {
+    let (flags, mut rest) = data.split_first().ok_or_else(|| {
+        CipherError::DecryptionError("wallet file truncated: flags missing".to_string())
+    })?;
+    if flags & !KNOWN_FLAGS != 0 {
+        return Err(CipherError::InvalidFormat(format!(
+            "unknown header flags {:#010b}",
+            flags & !KNOWN_FLAGS
+        )));
+    }
+
+    // content fingerprint
+    let mut fingerprint = None;
+    if flags & FLAG_FINGERPRINT != 0 {
+        let section = rest
+            .get(..FINGERPRINT_KEY_ID_SIZE + FINGERPRINT_SIZE)
+            .ok_or_else(|| {
+                CipherError::DecryptionError(
+                    "wallet file truncated: fingerprint missing or incomplete".to_string(),
+                )
+            })?;
+        let (key_id, value) = section.split_at(FINGERPRINT_KEY_ID_SIZE);
+        fingerprint = Some(Fingerprint {
+            key_id: key_id.try_into().expect("key id size checked above"),
+            value: value.try_into().expect("fingerprint size checked above"),
+        });
+        rest = &rest[section.len()..];
+    }
+
+    // scrypt parameters
+    let mut kdf = Kdf::Pbkdf2;
+    if flags & FLAG_SCRYPT != 0 {
+        let (log_n, params_rest) = rest.split_first().ok_or_else(|| {
+            CipherError::DecryptionError(
+                "wallet file truncated: scrypt parameters missing or incomplete".to_string(),
+            )
+        })?;
+        if *log_n > MAX_SCRYPT_LOG_N {
+            return Err(CipherError::InvalidFormat(format!(
+                "scrypt cost 2^{} above the maximum 2^{}",
+                log_n, MAX_SCRYPT_LOG_N
+            )));
+        }
+        let read_param = |input, max| {
+            U32VarIntDeserializer::new(Included(1), Included(max))
+                .deserialize::<DeserializeError>(input)
+                .map_err(|_| {
+                    CipherError::InvalidFormat(format!(
+                        "scrypt parameter missing or above the maximum {}",
+                        max
+                    ))
+                })
+        };
+        let (params_rest, r) = read_param(params_rest, MAX_SCRYPT_R)?;
+        let (params_rest, p) = read_param(params_rest, MAX_SCRYPT_P)?;
+        kdf = Kdf::Scrypt {
+            log_n: *log_n,
+            r,
+            p,
+        };
+        rest = params_rest;
+    }
+
+    // metadata
+    let mut metadata = None;
+    if flags & FLAG_METADATA != 0 {
+        let invalid =
+            || CipherError::InvalidFormat("metadata missing, incomplete or invalid".to_string());
+        let (metadata_rest, timestamp) =
+            U64VarIntDeserializer::new(Included(0), Included(u64::MAX))
+                .deserialize::<DeserializeError>(rest)
+                .map_err(|_| invalid())?;
+        let (metadata_rest, label_size) =
+            U32VarIntDeserializer::new(Included(0), Included(MAX_LABEL_SIZE))
+                .deserialize::<DeserializeError>(metadata_rest)
+                .map_err(|_| invalid())?;
+        let label = metadata_rest
+            .get(..label_size as usize)
+            .and_then(|label| std::str::from_utf8(label).ok())
+            .ok_or_else(invalid)?;
+        metadata = Some(Metadata {
+            timestamp,
+            label: label.to_string(),
+        });
+        rest = &metadata_rest[label_size as usize..];
+    }
+    Ok((rest, fingerprint, kdf, metadata))
+}
+
+/// Splits the header magic, version, flags and footer magic from encrypted data.
+///
+/// Data without header magic is only accepted as version 0,
+/// which also requires it to be long enough to hold a salt, a nonce and a tag.
+/// Versions above `VERSION` are rejected, as their format is unknown.
+pub(crate) fn read_envelope(data: &[u8]) -> Result<Envelope, CipherError> {
+    let version_deserializer = U32VarIntDeserializer::new(Included(0), Included(u32::MAX));
+    if let Some(rest) = data.strip_prefix(&HEADER_MAGIC) {
+        let (rest, version) = version_deserializer
+            .deserialize::<DeserializeError>(rest)
+            .map_err(|_| {
+                CipherError::DecryptionError(
+                    "wallet file truncated: version missing or incomplete".to_string(),
+                )
+            })?;
+        if version > VERSION {
+            return Err(CipherError::InvalidFormat(format!(
+                "unsupported version {}, above the current version {}",
+                version, VERSION
+            )));
+        }
+        let (rest, fingerprint, kdf, metadata) = if version >= 2 {
+            read_flags(rest)?
+        } else {
+            (rest, None, Kdf::Pbkdf2, None)
+        };
+        let aad = match metadata {
+            Some(_) => &data[..data.len() - rest.len()],
+            None => &[],
+        };
+        let (content, has_footer) = match rest.strip_suffix(&FOOTER_MAGIC) {
+            Some(content) => (content, true),
+            None => (rest, false),
+        };
+        Ok(Envelope {
+            version,
+            content,
+            has_footer,
+            fingerprint,
+            kdf,
+            metadata,
+            aad,
+        })
+    } else {
+        match version_deserializer.deserialize::<DeserializeError>(data) {
+            Ok((content, 0)) if content.len() >= ALGORITHM.min_content_len() => Ok(Envelope {
+                version: 0,
+                content,
+                has_footer: false,
+                fingerprint: None,
+                kdf: Kdf::Pbkdf2,
+                metadata: None,
+                aad: &[],
+            }),
+            _ => Err(CipherError::InvalidFormat(
+                "not a massa encrypted file".to_string(),
+            )),
+        }
+    }
+}
+
+/// Reads the version and framing of encrypted data without decrypting it.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn inspect(data: &[u8]) -> Result<CipherInfo, CipherError> {
+    let envelope = read_envelope(data)?;
+    Ok(CipherInfo {
+        version: envelope.version,
+        has_footer: envelope.has_footer,
+        has_fingerprint: envelope.fingerprint.is_some(),
+        kdf: envelope.kdf,
+        metadata: envelope.metadata,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encrypt::encrypt;
+
+    #[test]
+    fn test_inspect_complete() {
+        let encrypted = encrypt("password", b"content").unwrap();
+        let info = inspect(&encrypted).unwrap();
+        assert_eq!(info.version, VERSION);
+        assert!(info.has_footer);
+        assert!(!info.has_fingerprint);
+        assert_eq!(info.kdf, Kdf::Pbkdf2);
+        assert!(!info.is_truncated());
+    }
+
+    #[test]
+    fn test_inspect_scrypt() {
+        let kdf = Kdf::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        };
+        let encrypted = crate::encrypt_with_kdf("password", b"content", kdf).unwrap();
+        let info = inspect(&encrypted).unwrap();
+        assert_eq!(info.kdf, kdf);
+        assert!(!info.is_truncated());
+    }
+
+    #[test]
+    fn test_inspect_fingerprint() {
+        let encrypted = crate::encrypt_with_fingerprint("password", b"content").unwrap();
+        let info = inspect(&encrypted).unwrap();
+        assert!(info.has_fingerprint);
+        assert!(!info.is_truncated());
+    }
+
+    #[test]
+    fn test_inspect_metadata() {
+        let metadata = Metadata {
+            timestamp: 1_700_000_000,
+            label: "validator wallet".to_string(),
+        };
+        let encrypted = crate::encrypt_with_metadata("password", b"content", &metadata).unwrap();
+        let info = inspect(&encrypted).unwrap();
+        assert_eq!(info.metadata, Some(metadata));
+        assert!(!info.is_truncated());
+        assert_eq!(
+            inspect(&encrypt("password", b"content").unwrap())
+                .unwrap()
+                .metadata,
+            None
+        );
+    }
+
+    #[test]
+    fn test_inspect_unknown_flags() {
+        let mut encrypted = encrypt("password", b"content").unwrap();
+        // the header magic is followed by a single byte varint version, then the flags
+        encrypted[HEADER_MAGIC.len() + 1] = 0b1000_0000;
+        assert!(matches!(
+            inspect(&encrypted),
+            Err(CipherError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_inspect_future_version() {
+        let mut encrypted = encrypt("password", b"content").unwrap();
+        // the header magic is followed by a single byte varint version
+        encrypted[HEADER_MAGIC.len()] = (VERSION + 1) as u8;
+        assert!(matches!(
+            inspect(&encrypted),
+            Err(CipherError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            crate::decrypt::decrypt("password", &encrypted),
+            Err(CipherError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_inspect_truncated() {
+        let encrypted = encrypt("password", b"content").unwrap();
+        let info = inspect(&encrypted[..encrypted.len() - 10]).unwrap();
+        assert!(!info.has_footer);
+        assert!(info.is_truncated());
+        assert!(crate::decrypt::decrypt("password", &encrypted[..encrypted.len() - 10]).is_err());
+    }
+
+    #[test]
+    fn test_inspect_non_matching() {
+        assert!(matches!(
+            inspect(b"not encrypted at all"),
+            Err(CipherError::InvalidFormat(_))
+        ));
+    }
+}
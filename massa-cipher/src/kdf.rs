@@ -0,0 +1,63 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! massa-cipher key derivation module.
+//!
+//! The cipher key is derived from the password and the salt with `PBKDF2` by default,
+//! or with scrypt, whose parameters are then stored in the header.
+//!
+//! Read `lib.rs` module documentation for more information.
+
+use pbkdf2::password_hash::{PasswordHasher, Salt};
+use pbkdf2::Pbkdf2;
+
+use crate::constants::{HASH_PARAMS, MAX_SCRYPT_LOG_N, MAX_SCRYPT_P, MAX_SCRYPT_R};
+use crate::error::CipherError;
+
+/// Key derivation function turning the password into the cipher key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kdf {
+    /// `PBKDF2` with the parameters of `HASH_PARAMS`
+    Pbkdf2,
+    /// scrypt, as specified in [RFC 7914](https://datatracker.ietf.org/doc/html/rfc7914)
+    Scrypt {
+        /// base 2 logarithm of the CPU/memory cost
+        log_n: u8,
+        /// block size
+        r: u32,
+        /// parallelization
+        p: u32,
+    },
+}
+
+impl Kdf {
+    /// Derives the cipher key from the password and the salt.
+    ///
+    /// scrypt parameters above `MAX_SCRYPT_LOG_N`, `MAX_SCRYPT_R` or `MAX_SCRYPT_P` are refused.
+    pub(crate) fn derive_key(&self, password: &[u8], salt: &str) -> Result<Vec<u8>, CipherError> {
+        match *self {
+            Kdf::Pbkdf2 => {
+                let salt = Salt::new(salt).map_err(|e| CipherError::KdfError(e.to_string()))?;
+                let password_hash = Pbkdf2
+                    .hash_password_customized(password, None, None, HASH_PARAMS, salt)
+                    .map_err(|e| CipherError::KdfError(e.to_string()))?
+                    .hash
+                    .expect("content is missing after a successful hash");
+                Ok(password_hash.as_bytes().to_vec())
+            }
+            Kdf::Scrypt { log_n, r, p } => {
+                if log_n > MAX_SCRYPT_LOG_N || r > MAX_SCRYPT_R || p > MAX_SCRYPT_P {
+                    return Err(CipherError::KdfError(format!(
+                        "scrypt parameters 2^{}, {}, {} above the maximum 2^{}, {}, {}",
+                        log_n, r, p, MAX_SCRYPT_LOG_N, MAX_SCRYPT_R, MAX_SCRYPT_P
+                    )));
+                }
+                let params = scrypt::Params::new(log_n, r, p)
+                    .map_err(|e| CipherError::KdfError(e.to_string()))?;
+                let mut key = vec![0u8; HASH_PARAMS.output_length];
+                scrypt::scrypt(password, salt.as_bytes(), &params, &mut key)
+                    .map_err(|e| CipherError::KdfError(e.to_string()))?;
+                Ok(key)
+            }
+        }
+    }
+}
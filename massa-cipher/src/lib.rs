@@ -9,14 +9,61 @@
 //!
 //! To hash the password before using it as a cipher key, we use the `PBKDF2` key derivation function
 //! as specified in [RFC 2898](https://datatracker.ietf.org/doc/html/rfc2898).
+//! Where scrypt is required instead, `encrypt_with_kdf` records its parameters in the header,
+//! and `decrypt` derives the key accordingly.
+//! Passwords that are not valid UTF-8 can be given as raw bytes to `encrypt_bytes` and `decrypt_bytes`.
 //!
 //! The AES-GCM crate we use has received one security audit by NCC Group, with no significant findings.
+//!
+//! Encrypted data starts with a header magic and its version, and ends with a distinct footer magic,
+//! so that `inspect` can recognize it and detect truncation without the password.
+//!
+//! Decrypting many data encrypted with the same password and salt can reuse the derived cipher keys
+//! by going through a `KdfCache` with `decrypt_with_cache`.
+//!
+//! To share data with several recipients, `encrypt_multi` encrypts it once under a random data key
+//! and wraps that key separately for each recipient password.
+//!
+//! `encrypt_with_fingerprint` also stores an HMAC of the plaintext in the header, keyed by the password
+//! and masked with the per-file cipher key, so that `same_plaintext` can tell whether two encrypted data hold the same plaintext without decrypting them.
+//!
+//! `encrypt_and_sign_snapshot` appends a signature of the encrypted data by the operator's keypair,
+//! so that recipients of a bootstrap snapshot can verify its origin with `verify_and_decrypt_snapshot`.
+//!
+//! `encrypt_to_string` and `decrypt_from_string` handle encrypted data as prefixed URL-safe base64 text,
+//! to embed it in CLIs and configuration files.
+//!
+//! `encrypt_with_metadata` stores a timestamp and a label in the header, readable by `inspect` without the password.
+//! The header is then authenticated as associated data, so that tampering with the metadata makes decryption fail.
+//!
+//! `decrypt_with_config` can refuse data below a `min_accepted_version`, to force the migration of old files.
 
+mod algorithm;
+mod cache;
 mod constants;
 mod decrypt;
 mod encrypt;
 mod error;
+mod fingerprint;
+mod header;
+mod kdf;
+mod multi;
+mod snapshot;
+mod text;
 
-pub use decrypt::decrypt;
-pub use encrypt::encrypt;
+pub use algorithm::CipherAlgorithm;
+pub use cache::KdfCache;
+pub use decrypt::{
+    decrypt, decrypt_borrowed, decrypt_bytes, decrypt_with_cache, decrypt_with_config,
+    verify_password, DecryptConfig,
+};
+pub use encrypt::{encrypt, encrypt_bytes, encrypt_with_kdf, encrypt_with_metadata};
 pub use error::CipherError;
+pub use fingerprint::{encrypt_with_fingerprint, same_plaintext};
+pub use header::{inspect, CipherInfo, Metadata};
+pub use kdf::Kdf;
+pub use multi::{decrypt_multi, encrypt_multi};
+pub use snapshot::{
+    encrypt_and_sign_snapshot, sign_snapshot, verify_and_decrypt_snapshot, verify_snapshot,
+};
+pub use text::{decrypt_from_string, encrypt_to_string};
@@ -0,0 +1,267 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! massa-cipher multi-recipient module.
+//!
+//! The payload is encrypted once with AES-GCM under a random data key,
+//! and the data key is wrapped for each recipient password with [`encrypt`].
+//!
+//! Layout: `magic || version || recipient count || (wrap length || wrap)* || nonce || encrypted payload`
+//!
+//! Everything before the nonce is authenticated as associated data of the payload,
+//! so that wraps cannot be removed, added or reordered undetected.
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use rand::{thread_rng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::constants::{ALGORITHM, MAX_RECIPIENTS, MULTI_MAGIC, MULTI_VERSION, NONCE_SIZE};
+use crate::decrypt::decrypt;
+use crate::encrypt::encrypt;
+use crate::error::CipherError;
+use crate::header::inspect;
+use crate::kdf::Kdf;
+use massa_serialization::{
+    DeserializeError, Deserializer, Serializer, U32VarIntDeserializer, U32VarIntSerializer,
+};
+
+use std::ops::Bound::Included;
+
+/// Size of the random key the payload is encrypted with
+const DATA_KEY_SIZE: usize = 32;
+
+/// Encrypts `data` so that it can be decrypted with any of the given passwords.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn encrypt_multi(passwords: &[&str], data: &[u8]) -> Result<Vec<u8>, CipherError> {
+    if passwords.is_empty() {
+        return Err(CipherError::EncryptionError(
+            "at least one recipient password is required".to_string(),
+        ));
+    }
+    let recipient_count = u32::try_from(passwords.len())
+        .ok()
+        .filter(|count| *count <= MAX_RECIPIENTS)
+        .ok_or_else(|| {
+            CipherError::EncryptionError(format!("more than {} recipients", MAX_RECIPIENTS))
+        })?;
+
+    // generate the data key and the payload nonce
+    let mut data_key = Zeroizing::new([0u8; DATA_KEY_SIZE]);
+    thread_rng().fill_bytes(&mut data_key[..]);
+    let mut nonce_bytes = [0u8; NONCE_SIZE];
+    thread_rng().fill_bytes(&mut nonce_bytes);
+
+    // write the header, authenticated along with the payload
+    let u32_serializer = U32VarIntSerializer::new();
+    let mut content = MULTI_MAGIC.to_vec();
+    u32_serializer
+        .serialize(&MULTI_VERSION, &mut content)
+        .map_err(|err| CipherError::EncryptionError(err.to_string()))?;
+    u32_serializer
+        .serialize(&recipient_count, &mut content)
+        .map_err(|err| CipherError::EncryptionError(err.to_string()))?;
+    for password in passwords {
+        let wrapped_key = encrypt(password, &data_key[..])?;
+        u32_serializer
+            .serialize(&(wrapped_key.len() as u32), &mut content)
+            .map_err(|err| CipherError::EncryptionError(err.to_string()))?;
+        content.extend(wrapped_key);
+    }
+
+    // encrypt the payload
+    let cipher = Aes256Gcm::new_from_slice(&data_key[..]).expect("invalid key length");
+    let encrypted_bytes = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce_bytes),
+            Payload {
+                msg: data,
+                aad: &content,
+            },
+        )
+        .map_err(|e| CipherError::EncryptionError(e.to_string()))?;
+    content.extend(nonce_bytes);
+    content.extend(encrypted_bytes);
+    Ok(content)
+}
+
+/// Decrypts data produced by [`encrypt_multi`] with the password of one of its recipients.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn decrypt_multi(password: &str, data: &[u8]) -> Result<(u32, Vec<u8>), CipherError> {
+    let u32_deserializer = U32VarIntDeserializer::new(Included(0), Included(u32::MAX));
+
+    // parse magic, format version and recipient count
+    let rest = data.strip_prefix(&MULTI_MAGIC).ok_or_else(|| {
+        CipherError::InvalidFormat("not a massa multi-recipient encrypted file".to_string())
+    })?;
+    let (rest, version) = u32_deserializer
+        .deserialize::<DeserializeError>(rest)
+        .map_err(|_| {
+            CipherError::DecryptionError(
+                "encrypted data truncated: version missing or incomplete".to_string(),
+            )
+        })?;
+    if version != MULTI_VERSION {
+        return Err(CipherError::InvalidFormat(format!(
+            "unsupported multi-recipient version {}",
+            version
+        )));
+    }
+    let (mut rest, recipient_count) =
+        U32VarIntDeserializer::new(Included(1), Included(MAX_RECIPIENTS))
+            .deserialize::<DeserializeError>(rest)
+            .map_err(|_| {
+                CipherError::DecryptionError(format!(
+                    "recipient count missing, or not between 1 and {}",
+                    MAX_RECIPIENTS
+                ))
+            })?;
+
+    // find the data key wrapped for this password
+    let mut data_key: Option<Zeroizing<Vec<u8>>> = None;
+    for _ in 0..recipient_count {
+        let (wrap_rest, wrap_len) = u32_deserializer
+            .deserialize::<DeserializeError>(rest)
+            .map_err(|_| {
+                CipherError::DecryptionError(
+                    "encrypted data truncated: wrapped key length missing or incomplete"
+                        .to_string(),
+                )
+            })?;
+        let wrapped_key = wrap_rest.get(..wrap_len as usize).ok_or_else(|| {
+            CipherError::DecryptionError(
+                "encrypted data truncated: wrapped key missing or incomplete".to_string(),
+            )
+        })?;
+        rest = &wrap_rest[wrap_len as usize..];
+        // keys are only wrapped with PBKDF2: refuse costlier derivations requested by the data
+        if data_key.is_none() && inspect(wrapped_key)?.kdf == Kdf::Pbkdf2 {
+            if let Ok((_, key)) = decrypt(password, wrapped_key) {
+                data_key = Some(Zeroizing::new(key));
+            }
+        }
+    }
+    let header = &data[..data.len() - rest.len()];
+    let data_key = data_key.ok_or_else(|| {
+        CipherError::DecryptionError("wrong password or corrupted data".to_string())
+    })?;
+    let cipher = Aes256Gcm::new_from_slice(&data_key).map_err(|_| {
+        CipherError::DecryptionError("corrupted data: invalid data key".to_string())
+    })?;
+
+    // parse AES-GCM nonce
+    let nonce = Nonce::from_slice(rest.get(..NONCE_SIZE).ok_or_else(|| {
+        CipherError::DecryptionError(
+            "encrypted data truncated: nonce missing or incomplete".to_string(),
+        )
+    })?);
+
+    // decrypt the payload, authenticating the header
+    let encrypted_bytes = rest
+        .get(NONCE_SIZE..)
+        .filter(|encrypted| encrypted.len() >= ALGORITHM.tag_len())
+        .ok_or_else(|| {
+            CipherError::DecryptionError(
+                "encrypted data truncated: payload missing or incomplete".to_string(),
+            )
+        })?;
+    let decrypted_bytes = cipher
+        .decrypt(
+            nonce,
+            Payload {
+                msg: encrypted_bytes,
+                aad: header,
+            },
+        )
+        .map_err(|_| CipherError::DecryptionError("corrupted data".to_string()))?;
+    Ok((version, decrypted_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_recipient_roundtrip() {
+        let data = b"bootstrap snapshot";
+        let encrypted = encrypt_multi(&["first password", "second password"], data).unwrap();
+
+        let (version, decrypted) = decrypt_multi("first password", &encrypted).unwrap();
+        assert_eq!(version, MULTI_VERSION);
+        assert_eq!(decrypted, data);
+        let (_, decrypted) = decrypt_multi("second password", &encrypted).unwrap();
+        assert_eq!(decrypted, data);
+
+        assert!(decrypt_multi("third password", &encrypted).is_err());
+    }
+
+    /// Splits multi-recipient data into its header before the wraps, its wraps and its payload
+    fn split_wraps(data: &[u8]) -> (Vec<u8>, Vec<Vec<u8>>, Vec<u8>) {
+        let u32_deserializer = U32VarIntDeserializer::new(Included(0), Included(u32::MAX));
+        let (rest, _) = u32_deserializer
+            .deserialize::<DeserializeError>(&data[MULTI_MAGIC.len()..])
+            .unwrap();
+        let (mut rest, count) = u32_deserializer
+            .deserialize::<DeserializeError>(rest)
+            .unwrap();
+        let prefix = data[..data.len() - rest.len()].to_vec();
+        let mut wraps = Vec::new();
+        for _ in 0..count {
+            let start = rest;
+            let (wrap_rest, wrap_len) = u32_deserializer
+                .deserialize::<DeserializeError>(rest)
+                .unwrap();
+            rest = &wrap_rest[wrap_len as usize..];
+            wraps.push(start[..start.len() - rest.len()].to_vec());
+        }
+        (prefix, wraps, rest.to_vec())
+    }
+
+    #[test]
+    fn test_multi_recipient_header_authenticated() {
+        let encrypted = encrypt_multi(&["first password", "second password"], b"data").unwrap();
+        let (mut prefix, wraps, payload) = split_wraps(&encrypted);
+
+        // reordered wraps
+        let mut reordered = prefix.clone();
+        reordered.extend(wraps[1].iter().chain(&wraps[0]));
+        reordered.extend(&payload);
+        assert!(decrypt_multi("first password", &reordered).is_err());
+
+        // stripped wrap, with a consistent recipient count
+        *prefix.last_mut().unwrap() = 1;
+        let mut stripped = prefix;
+        stripped.extend(&wraps[0]);
+        stripped.extend(&payload);
+        assert!(decrypt_multi("first password", &stripped).is_err());
+    }
+
+    #[test]
+    fn test_multi_recipient_format_checks() {
+        let encrypted = encrypt_multi(&["password"], b"data").unwrap();
+
+        let mut wrong_magic = encrypted.clone();
+        wrong_magic[0] ^= 1;
+        assert!(matches!(
+            decrypt_multi("password", &wrong_magic),
+            Err(CipherError::InvalidFormat(_))
+        ));
+
+        // the single byte varint version follows the magic
+        let mut wrong_version = encrypted.clone();
+        wrong_version[MULTI_MAGIC.len()] = MULTI_VERSION as u8 + 1;
+        assert!(matches!(
+            decrypt_multi("password", &wrong_version),
+            Err(CipherError::InvalidFormat(_))
+        ));
+
+        // then the single byte varint recipient count
+        let mut too_many = encrypted;
+        too_many[MULTI_MAGIC.len() + 1] = MAX_RECIPIENTS as u8 + 1;
+        assert!(decrypt_multi("password", &too_many).is_err());
+
+        let passwords = vec!["password"; MAX_RECIPIENTS as usize + 1];
+        assert!(encrypt_multi(&passwords, b"data").is_err());
+    }
+}
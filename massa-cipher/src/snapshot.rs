@@ -0,0 +1,103 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! massa-cipher snapshot module.
+//!
+//! AES-GCM only proves that the sender knew the password. To let recipients of a bootstrap snapshot
+//! verify its origin, the encrypted snapshot is also signed with the keypair of the operator
+//! distributing it, and the signature is appended to it: `encrypted snapshot || signature`.
+//!
+//! Read `lib.rs` module documentation for more information.
+
+use massa_hash::Hash;
+use massa_signature::{KeyPair, PublicKey, Signature};
+
+use crate::decrypt::decrypt;
+use crate::encrypt::encrypt;
+use crate::error::CipherError;
+
+/// Signs the hash of `snapshot_bytes` with `keypair`
+pub fn sign_snapshot(keypair: &KeyPair, snapshot_bytes: &[u8]) -> Result<Signature, CipherError> {
+    Ok(keypair.sign(&Hash::compute_from(snapshot_bytes))?)
+}
+
+/// Checks that `signature` was produced by the keypair of `public_key` over `snapshot_bytes`
+pub fn verify_snapshot(
+    public_key: &PublicKey,
+    snapshot_bytes: &[u8],
+    signature: &Signature,
+) -> Result<(), CipherError> {
+    Ok(public_key.verify_signature(&Hash::compute_from(snapshot_bytes), signature)?)
+}
+
+/// Encrypts `snapshot_bytes` with `password` and appends the signature of the encrypted data by `keypair`
+pub fn encrypt_and_sign_snapshot(
+    password: &str,
+    keypair: &KeyPair,
+    snapshot_bytes: &[u8],
+) -> Result<Vec<u8>, CipherError> {
+    let mut content = encrypt(password, snapshot_bytes)?;
+    let signature = sign_snapshot(keypair, &content)?;
+    content.extend(signature.to_bytes());
+    Ok(content)
+}
+
+/// Verifies the signature of a snapshot produced by `encrypt_and_sign_snapshot` against `public_key`,
+/// then decrypts it with `password`.
+///
+/// The signature is checked first, so that data from an unexpected origin is never decrypted.
+pub fn verify_and_decrypt_snapshot(
+    password: &str,
+    public_key: &PublicKey,
+    data: &[u8],
+) -> Result<Vec<u8>, CipherError> {
+    let signature_ser_len = Signature::get_ser_len_for(public_key);
+    if data.len() < signature_ser_len {
+        return Err(CipherError::InvalidFormat(
+            "snapshot too short to hold a signature".to_string(),
+        ));
+    }
+    let (encrypted, signature_bytes) = data.split_at(data.len() - signature_ser_len);
+    let signature = Signature::from_bytes(signature_bytes)?;
+    verify_snapshot(public_key, encrypted, &signature)?;
+    let (_, snapshot_bytes) = decrypt(password, encrypted)?;
+    Ok(snapshot_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let keypair = KeyPair::generate(0).unwrap();
+        let data = encrypt_and_sign_snapshot("password", &keypair, b"snapshot").unwrap();
+        let snapshot =
+            verify_and_decrypt_snapshot("password", &keypair.get_public_key(), &data).unwrap();
+        assert_eq!(snapshot, b"snapshot");
+
+        let signature = sign_snapshot(&keypair, b"snapshot").unwrap();
+        verify_snapshot(&keypair.get_public_key(), b"snapshot", &signature).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_tampered() {
+        let keypair = KeyPair::generate(0).unwrap();
+        let mut data = encrypt_and_sign_snapshot("password", &keypair, b"snapshot").unwrap();
+        data[10] ^= 1;
+        assert!(matches!(
+            verify_and_decrypt_snapshot("password", &keypair.get_public_key(), &data),
+            Err(CipherError::SignatureError(_))
+        ));
+
+        let signature = sign_snapshot(&keypair, b"snapshot").unwrap();
+        assert!(verify_snapshot(&keypair.get_public_key(), b"snapshoT", &signature).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_other_signer() {
+        let keypair = KeyPair::generate(0).unwrap();
+        let other = KeyPair::generate(0).unwrap();
+        let data = encrypt_and_sign_snapshot("password", &keypair, b"snapshot").unwrap();
+        assert!(verify_and_decrypt_snapshot("password", &other.get_public_key(), &data).is_err());
+    }
+}
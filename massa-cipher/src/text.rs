@@ -0,0 +1,77 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! massa-cipher text module.
+//!
+//! Encrypted data can be embedded as text in CLIs and configuration files:
+//! `TEXT_PREFIX` followed by the URL-safe base64 encoding (without padding) of the binary encrypted data.
+//!
+//! Read `lib.rs` module documentation for more information.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use crate::constants::TEXT_PREFIX;
+use crate::decrypt::decrypt;
+use crate::encrypt::encrypt;
+use crate::error::CipherError;
+
+/// Encrypts `data` and returns it in textual form.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn encrypt_to_string(password: &str, data: &[u8]) -> Result<String, CipherError> {
+    let encrypted = encrypt(password, data)?;
+    Ok(format!(
+        "{}{}",
+        TEXT_PREFIX,
+        URL_SAFE_NO_PAD.encode(encrypted)
+    ))
+}
+
+/// Decrypts encrypted data given in the textual form produced by `encrypt_to_string`.
+///
+/// Read `lib.rs` module documentation for more information.
+pub fn decrypt_from_string(password: &str, s: &str) -> Result<(u32, Vec<u8>), CipherError> {
+    let encoded = s
+        .trim()
+        .strip_prefix(TEXT_PREFIX)
+        .ok_or_else(|| CipherError::InvalidFormat(format!("missing {} prefix", TEXT_PREFIX)))?;
+    let encrypted = URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| CipherError::InvalidFormat(format!("invalid base64: {}", e)))?;
+    decrypt(password, &encrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_roundtrip() {
+        let s = encrypt_to_string("password", b"content").unwrap();
+        assert!(s.starts_with(TEXT_PREFIX));
+        assert!(s
+            .chars()
+            .skip(TEXT_PREFIX.len())
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        let (_, data) = decrypt_from_string("password", &s).unwrap();
+        assert_eq!(data, b"content");
+    }
+
+    #[test]
+    fn test_string_wrong_prefix() {
+        let s = encrypt_to_string("password", b"content").unwrap();
+        let s = s.replacen(TEXT_PREFIX, "massacypher:", 1);
+        assert!(matches!(
+            decrypt_from_string("password", &s),
+            Err(CipherError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_string_invalid_encoding() {
+        let s = format!("{}not*base64", TEXT_PREFIX);
+        assert!(matches!(
+            decrypt_from_string("password", &s),
+            Err(CipherError::InvalidFormat(_))
+        ));
+    }
+}
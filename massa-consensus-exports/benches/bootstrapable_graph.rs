@@ -0,0 +1,116 @@
+#[cfg(feature = "benchmarking")]
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[cfg(feature = "benchmarking")]
+fn criterion_benchmark(c: &mut Criterion) {
+    use massa_consensus_exports::bootstrapable_graph::{
+        BootstrapableGraph, BootstrapableGraphDeserializer, BootstrapableGraphSerializer,
+    };
+    use massa_consensus_exports::export_active_block::ExportActiveBlock;
+    use massa_hash::Hash;
+    use massa_models::{
+        block::{Block, BlockDeserializerArgs, BlockSerializer},
+        block_header::{BlockHeader, BlockHeaderSerializer},
+        block_id::BlockId,
+        operation::OperationId,
+        secure_share::{Id, SecureShareContent},
+        slot::Slot,
+    };
+    use massa_serialization::DeserializeError;
+    use massa_signature::KeyPair;
+
+    const THREAD_COUNT: u8 = 32;
+    const PERIODS: u64 = 16;
+    const OPERATION_COUNT: usize = 1000;
+
+    /// This function is used to prepare the data for the benchmarks
+    /// It builds a graph of blocks referencing the latest block of each thread,
+    /// and serializes it with each block compressed independently.
+    fn prepare_bench_function() -> (Vec<u8>, BootstrapableGraphDeserializer) {
+        let keypair = KeyPair::generate(0).unwrap();
+        let mut final_blocks = Vec::new();
+        let mut latest: Vec<(BlockId, u64)> = Vec::new();
+        for period in 0..=PERIODS {
+            let parents = latest.clone();
+            for thread in 0..THREAD_COUNT {
+                let header = BlockHeader::new_verifiable(
+                    BlockHeader {
+                        current_version: 0,
+                        announced_version: 0,
+                        slot: Slot::new(period, thread),
+                        parents: parents.iter().map(|(id, _)| *id).collect(),
+                        operation_merkle_root: Hash::compute_from(&Vec::new()),
+                        endorsements: Vec::new(),
+                        denunciations: Vec::new(),
+                    },
+                    BlockHeaderSerializer::new(),
+                    &keypair,
+                )
+                .unwrap();
+                let operations = (0..OPERATION_COUNT)
+                    .map(|index| OperationId::new(Hash::compute_from(&index.to_be_bytes())))
+                    .collect();
+                let block = Block::new_verifiable(
+                    Block { header, operations },
+                    BlockSerializer::new(),
+                    &keypair,
+                )
+                .unwrap();
+                if period == 0 {
+                    latest.push((block.id, period));
+                } else {
+                    latest[thread as usize] = (block.id, period);
+                }
+                final_blocks.push(ExportActiveBlock {
+                    block,
+                    parents: parents.clone(),
+                    is_final: true,
+                });
+            }
+        }
+        let graph = BootstrapableGraph { final_blocks };
+
+        let mut buffer = Vec::new();
+        BootstrapableGraphSerializer::new()
+            .serialize_compressed_blocks(&graph, &mut buffer)
+            .unwrap();
+        let deserializer = BootstrapableGraphDeserializer::new(
+            BlockDeserializerArgs {
+                thread_count: THREAD_COUNT,
+                max_operations_per_block: OPERATION_COUNT as u32,
+                endorsement_count: 16,
+                max_denunciations_per_block_header: 16,
+                last_start_period: Some(0),
+            },
+            graph.final_blocks.len() as u32,
+        );
+        (buffer, deserializer)
+    }
+
+    let (buffer, deserializer) = prepare_bench_function();
+    c.bench_function("Deserialize compressed blocks sequentially", |b| {
+        b.iter(|| {
+            deserializer
+                .deserialize_compressed_blocks::<DeserializeError>(black_box(&buffer))
+                .unwrap()
+        })
+    });
+    c.bench_function("Deserialize compressed blocks in parallel", |b| {
+        b.iter(|| {
+            deserializer
+                .deserialize_parallel::<DeserializeError>(black_box(&buffer))
+                .unwrap()
+        })
+    });
+}
+
+#[cfg(feature = "benchmarking")]
+criterion_group!(benches, criterion_benchmark);
+
+#[cfg(feature = "benchmarking")]
+criterion_main!(benches);
+
+#[cfg(not(feature = "benchmarking"))]
+fn main() {
+    println!("Please use the `--features benchmarking` flag to run this benchmark.");
+}
@@ -1,14 +1,66 @@
+use crate::checksum::ChecksumHash;
+use crate::error::GraphError;
 use crate::export_active_block::{
-    ExportActiveBlock, ExportActiveBlockDeserializer, ExportActiveBlockSerializer,
+    ExportActiveBlock, ExportActiveBlockDeserializer, ExportActiveBlockHeader,
+    ExportActiveBlockSerializer,
+};
+use crate::header_chain::BlockHeaderLight;
+use crate::merkle::{compute_proof, compute_root, leaf_hash, FinalityProof, MerkleProof};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use massa_hash::{Hash, HashDeserializer, HASH_SIZE_BYTES};
+use massa_models::{
+    block::BlockDeserializerArgs,
+    block_id::BlockId,
+    clique::CliquesDeserializer,
+    config::{
+        ENDORSEMENT_COUNT, MAX_BLOCK_SIZE, MAX_BOOTSTRAP_BLOCKS, MAX_BOOTSTRAP_CLIQUES,
+        MAX_BOOTSTRAP_MESSAGE_SIZE, MAX_DENUNCIATIONS_PER_BLOCK_HEADER, MAX_OPERATIONS_PER_BLOCK,
+        PERIODS_PER_CYCLE, POS_SAVED_CYCLES, THREAD_COUNT,
+    },
+    endorsement::EndorsementId,
+    prehash::{PreHashMap, PreHashSet},
+    slot::Slot,
 };
-use massa_models::block::BlockDeserializerArgs;
 use massa_serialization::{
-    Deserializer, SerializeError, Serializer, U32VarIntDeserializer, U32VarIntSerializer,
+    DeserializeError, Deserializer, OptionDeserializer, OptionSerializer, SerializeError,
+    Serializer, U32VarIntDeserializer, U32VarIntSerializer, U64VarIntDeserializer,
+    U64VarIntSerializer,
+};
+use nom::error::{ContextError, ErrorKind, ParseError};
+use nom::{
+    bytes::complete::take,
+    combinator::{all_consuming, consumed},
+    error::context,
+    multi::{count, length_count, length_data},
+    sequence::tuple,
+    IResult, Parser,
 };
-use nom::error::{ContextError, ParseError};
-use nom::{error::context, multi::length_count, sequence::tuple, IResult, Parser};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Read, Write};
 use std::ops::Bound::Included;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Maximum number of final blocks compressed by `BootstrapableGraph::sample_compression_ratio`
+const COMPRESSION_SAMPLE_SIZE: usize = 16;
+
+/// Lower bound of the size of a serialized `ExportActiveBlock`. The block itself only writes
+/// its content, so this is the smallest genesis block with no endorsement or operation.
+const MIN_EXPORT_ACTIVE_BLOCK_SIZE: usize =
+    // header signature and creator public key, with their version varints
+    (1 + 64) + (1 + 32)
+    // header versions, slot, parents flag, operation merkle root, endorsement and denunciation counts
+    + 1 + 1 + (1 + 1) + 1 + HASH_SIZE_BYTES + 1 + 1
+    // operation count, parents flag and finality of the block
+    + 1 + 1 + 1;
+
+/// Maximum size of a serialized `u32` varint, such as the final block count
+const U32_VARINT_MAX_SIZE: usize = 5;
 
 /// Bootstrap graph
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,11 +69,637 @@ pub struct BootstrapableGraph {
     pub final_blocks: Vec<ExportActiveBlock>,
 }
 
+/// Thread and period ranges covered by a graph written by `BootstrapableGraph::serialize_window`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphWindow {
+    /// covered threads
+    pub threads: Range<u8>,
+    /// covered periods
+    pub periods: Range<u64>,
+}
+
+impl GraphWindow {
+    /// Returns true if `slot` is in the window
+    pub fn contains(&self, slot: &Slot) -> bool {
+        self.threads.contains(&slot.thread) && self.periods.contains(&slot.period)
+    }
+}
+
+/// Overview of the content of a `BootstrapableGraph`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphStats {
+    /// number of final blocks
+    pub block_count: usize,
+    /// number of final blocks in each thread
+    pub blocks_per_thread: Vec<usize>,
+    /// number of operations referenced by the final blocks
+    pub operation_count: usize,
+    /// number of endorsements included in the final blocks
+    pub endorsement_count: usize,
+    /// highest period among the final blocks, `None` if there is no block
+    pub highest_period: Option<u64>,
+    /// size in bytes of the serialized graph, `None` if the graph cannot be serialized
+    pub serialized_size: Option<usize>,
+}
+
+impl BootstrapableGraph {
+    /// Gathers block, operation and endorsement counts of the graph.
+    ///
+    /// Blocks with a thread outside of `0..thread_count` are counted in the totals only.
+    pub fn stats(&self, thread_count: u8) -> GraphStats {
+        let mut blocks_per_thread = vec![0; thread_count as usize];
+        let mut operation_count = 0;
+        let mut endorsement_count = 0;
+        let mut highest_period = None;
+        for export_active_block in &self.final_blocks {
+            let block = &export_active_block.block.content;
+            let slot = block.header.content.slot;
+            if let Some(count) = blocks_per_thread.get_mut(slot.thread as usize) {
+                *count += 1;
+            }
+            operation_count += block.operations.len();
+            endorsement_count += block.header.content.endorsements.len();
+            highest_period = highest_period.max(Some(slot.period));
+        }
+
+        let mut buffer = Vec::new();
+        let serialized_size = BootstrapableGraphSerializer::new()
+            .serialize(self, &mut buffer)
+            .ok()
+            .map(|_| buffer.len());
+
+        GraphStats {
+            block_count: self.final_blocks.len(),
+            blocks_per_thread,
+            operation_count,
+            endorsement_count,
+            highest_period,
+            serialized_size,
+        }
+    }
+
+    /// Estimates the ratio of compressed to uncompressed size of the final blocks compressed
+    /// one by one, as written by `BootstrapableGraphSerializer::serialize_compressed_blocks`.
+    ///
+    /// Only up to `COMPRESSION_SAMPLE_SIZE` blocks, evenly spread over `final_blocks`, are compressed.
+    /// An empty graph has a ratio of 1.
+    pub fn sample_compression_ratio(
+        &self,
+        serializer: &ExportActiveBlockSerializer,
+    ) -> Result<f32, SerializeError> {
+        let step = (self.final_blocks.len() / COMPRESSION_SAMPLE_SIZE).max(1);
+        let mut serialized_len = 0;
+        let mut compressed_len = 0;
+        let mut serialized_block = Vec::new();
+        for export_active_block in self.final_blocks.iter().step_by(step) {
+            serialized_block.clear();
+            serializer.serialize(export_active_block, &mut serialized_block)?;
+            serialized_len += serialized_block.len();
+            compressed_len += compress_block(&serialized_block)?.len();
+        }
+        if serialized_len == 0 {
+            return Ok(1.0);
+        }
+        Ok(compressed_len as f32 / serialized_len as f32)
+    }
+
+    /// Computes the Merkle root of the final blocks.
+    ///
+    /// Each leaf is the hash of a serialized `ExportActiveBlock`, in the order of `final_blocks`.
+    pub fn merkle_root(&self) -> Result<Hash, SerializeError> {
+        Ok(compute_root(self.merkle_leaves()?))
+    }
+
+    /// Builds the proof that the block `block_id` is part of the Merkle tree of the graph.
+    /// Returns `None` if the block is not in the graph.
+    pub fn merkle_proof(&self, block_id: &BlockId) -> Result<Option<MerkleProof>, SerializeError> {
+        let Some(index) = self
+            .final_blocks
+            .iter()
+            .position(|export_active_block| &export_active_block.block.id == block_id)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(compute_proof(self.merkle_leaves()?, index)))
+    }
+
+    /// Builds the proof that the block `block_id` is final, to be checked by a light client
+    /// against the Merkle root of the graph with `verify_finality_proof`.
+    ///
+    /// The descendants of the block are taken breadth first until their total fitness is above `delta_f0`,
+    /// the fitness threshold of finality. Fails with `GraphError::NotFinal` if the block is not marked final
+    /// or if its descendants in the graph do not reach that threshold.
+    pub fn finality_proof(
+        &self,
+        block_id: &BlockId,
+        delta_f0: u64,
+    ) -> Result<FinalityProof, GraphError> {
+        let block = self
+            .final_blocks
+            .iter()
+            .find(|export_active_block| &export_active_block.block.id == block_id)
+            .ok_or(GraphError::UnknownBlock(*block_id))?;
+        if !block.is_final {
+            return Err(GraphError::NotFinal(*block_id));
+        }
+
+        // children of each block within the graph
+        let mut children: PreHashMap<BlockId, Vec<&ExportActiveBlock>> = PreHashMap::default();
+        for export_active_block in &self.final_blocks {
+            for (parent_id, _) in &export_active_block.parents {
+                children
+                    .entry(*parent_id)
+                    .or_default()
+                    .push(export_active_block);
+            }
+        }
+
+        let mut descendants = Vec::new();
+        let mut visited: PreHashSet<BlockId> = PreHashSet::default();
+        let mut to_visit = VecDeque::from([*block_id]);
+        let mut fitness: u64 = 0;
+        'search: while let Some(id) = to_visit.pop_front() {
+            for child in children.get(&id).into_iter().flatten() {
+                if fitness > delta_f0 {
+                    break 'search;
+                }
+                if !visited.insert(child.block.id) {
+                    continue;
+                }
+                fitness = fitness.checked_add(child.block.get_fitness()).ok_or(
+                    GraphError::FitnessOverflow(child.block.content.header.content.slot.thread),
+                )?;
+                descendants.push(*child);
+                to_visit.push_back(child.block.id);
+            }
+        }
+        if fitness <= delta_f0 {
+            return Err(GraphError::NotFinal(*block_id));
+        }
+
+        let leaves = self.merkle_leaves()?;
+        let indexes: PreHashMap<BlockId, usize> = self
+            .final_blocks
+            .iter()
+            .enumerate()
+            .map(|(index, export_active_block)| (export_active_block.block.id, index))
+            .collect();
+        let proof_of = |id: &BlockId| compute_proof(leaves.clone(), indexes[id]);
+        Ok(FinalityProof {
+            block: block.clone(),
+            merkle_proof: proof_of(block_id),
+            descendants: descendants
+                .into_iter()
+                .map(|descendant| (descendant.clone(), proof_of(&descendant.block.id)))
+                .collect(),
+        })
+    }
+
+    /// Returns the final blocks ordered so that every block comes after its parents.
+    ///
+    /// Parents that are not part of the graph are ignored.
+    /// Blocks without dependencies between them keep their relative order in `final_blocks`.
+    pub fn iter_topological(&self) -> Result<Vec<&ExportActiveBlock>, GraphError> {
+        let indexes: PreHashMap<BlockId, usize> = self
+            .final_blocks
+            .iter()
+            .enumerate()
+            .map(|(index, export_active_block)| (export_active_block.block.id, index))
+            .collect();
+
+        // count the parents of each block inside the graph, and list its children
+        let mut pending_parents = vec![0usize; self.final_blocks.len()];
+        let mut children = vec![Vec::new(); self.final_blocks.len()];
+        for (index, export_active_block) in self.final_blocks.iter().enumerate() {
+            for (parent_id, _) in &export_active_block.parents {
+                if let Some(&parent_index) = indexes.get(parent_id) {
+                    pending_parents[index] += 1;
+                    children[parent_index].push(index);
+                }
+            }
+        }
+
+        let mut ready: VecDeque<usize> = pending_parents
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count == 0)
+            .map(|(index, _)| index)
+            .collect();
+        let mut sorted = Vec::with_capacity(self.final_blocks.len());
+        while let Some(index) = ready.pop_front() {
+            sorted.push(&self.final_blocks[index]);
+            for &child_index in &children[index] {
+                pending_parents[child_index] -= 1;
+                if pending_parents[child_index] == 0 {
+                    ready.push_back(child_index);
+                }
+            }
+        }
+
+        // blocks that still wait for a parent are part of a cycle or descend from one
+        if let Some(index) = pending_parents.iter().position(|count| *count > 0) {
+            return Err(GraphError::ParentCycle(self.final_blocks[index].block.id));
+        }
+        Ok(sorted)
+    }
+
+    /// Finds the lowest common ancestor of blocks `a` and `b`: among the blocks that are both `a` or one
+    /// of its ancestors and `b` or one of its ancestors, the one with the highest slot.
+    ///
+    /// Only the final blocks of the graph are walked: parents outside of the graph are ignored.
+    /// Returns `None` if the two blocks have no common ancestor within the graph.
+    pub fn common_ancestor(&self, a: &BlockId, b: &BlockId) -> Result<Option<BlockId>, GraphError> {
+        let blocks: PreHashMap<BlockId, &ExportActiveBlock> = self
+            .final_blocks
+            .iter()
+            .map(|export_active_block| (export_active_block.block.id, export_active_block))
+            .collect();
+        let ancestors_of = |id: &BlockId| -> Result<PreHashSet<BlockId>, GraphError> {
+            if !blocks.contains_key(id) {
+                return Err(GraphError::UnknownBlock(*id));
+            }
+            let mut ancestors = PreHashSet::default();
+            let mut to_visit = vec![*id];
+            while let Some(id) = to_visit.pop() {
+                if let Some(export_active_block) = blocks.get(&id) {
+                    if ancestors.insert(id) {
+                        to_visit.extend(export_active_block.parents.iter().map(|(id, _)| *id));
+                    }
+                }
+            }
+            Ok(ancestors)
+        };
+        let ancestors_a = ancestors_of(a)?;
+        let ancestors_b = ancestors_of(b)?;
+        Ok(ancestors_a
+            .intersection(&ancestors_b)
+            .max_by_key(|id| (blocks[*id].block.content.header.content.slot, **id))
+            .copied())
+    }
+
+    /// Returns the light headers of the final blocks, in the same order,
+    /// to be sent with `HeaderChainSerializer` to clients that do not need the block bodies
+    pub fn header_chain(&self) -> Vec<BlockHeaderLight> {
+        self.final_blocks
+            .iter()
+            .map(BlockHeaderLight::from)
+            .collect()
+    }
+
+    /// Returns the final block with the highest fitness, the one with the smallest id among ties,
+    /// or `None` if the graph is empty.
+    pub fn best_block(&self) -> Option<&ExportActiveBlock> {
+        self.final_blocks.iter().max_by_key(|export_active_block| {
+            (
+                export_active_block.block.get_fitness(),
+                Reverse(export_active_block.block.id),
+            )
+        })
+    }
+
+    /// Sums the fitness of the final blocks of each thread.
+    ///
+    /// Blocks with a thread outside of `0..thread_count` are ignored.
+    pub fn thread_fitness(&self, thread_count: u8) -> Result<Vec<u64>, GraphError> {
+        let mut fitness = vec![0u64; thread_count as usize];
+        for export_active_block in &self.final_blocks {
+            let thread = export_active_block.block.content.header.content.slot.thread;
+            if let Some(thread_fitness) = fitness.get_mut(thread as usize) {
+                *thread_fitness = thread_fitness
+                    .checked_add(export_active_block.block.get_fitness())
+                    .ok_or(GraphError::FitnessOverflow(thread))?;
+            }
+        }
+        Ok(fitness)
+    }
+
+    /// Returns a subgraph of the most recent final blocks whose total fitness reaches `target_fitness`,
+    /// closed under parents so that it passes `validate_parent_closure`.
+    ///
+    /// Blocks are taken from the highest slots down until the target is reached,
+    /// then the ancestors of the selected blocks are added, so the total fitness can exceed the target.
+    /// Fails with `GraphError::MissingParent` if one of these ancestors is not in this graph.
+    /// The selected blocks keep their order in `final_blocks`.
+    pub fn minimal_for_fitness(
+        &self,
+        target_fitness: u64,
+    ) -> Result<BootstrapableGraph, GraphError> {
+        let blocks: PreHashMap<BlockId, &ExportActiveBlock> = self
+            .final_blocks
+            .iter()
+            .map(|export_active_block| (export_active_block.block.id, export_active_block))
+            .collect();
+        let mut newest_first: Vec<&ExportActiveBlock> = self.final_blocks.iter().collect();
+        newest_first.sort_unstable_by_key(|export_active_block| {
+            Reverse((
+                export_active_block.block.content.header.content.slot,
+                export_active_block.block.id,
+            ))
+        });
+        let add_fitness = |fitness: u64, export_active_block: &ExportActiveBlock| {
+            fitness
+                .checked_add(export_active_block.block.get_fitness())
+                .ok_or(GraphError::FitnessOverflow(
+                    export_active_block.block.content.header.content.slot.thread,
+                ))
+        };
+
+        let mut fitness: u64 = 0;
+        let mut selected: PreHashSet<BlockId> = PreHashSet::default();
+        let mut to_visit: Vec<&ExportActiveBlock> = Vec::new();
+        for export_active_block in newest_first {
+            if fitness >= target_fitness {
+                break;
+            }
+            fitness = add_fitness(fitness, export_active_block)?;
+            selected.insert(export_active_block.block.id);
+            to_visit.push(export_active_block);
+        }
+        if fitness < target_fitness {
+            return Err(GraphError::InsufficientFitness(target_fitness, fitness));
+        }
+
+        // add the missing ancestors of the selected blocks
+        while let Some(export_active_block) = to_visit.pop() {
+            for (parent_id, _) in &export_active_block.parents {
+                if selected.contains(parent_id) {
+                    continue;
+                }
+                let parent = blocks.get(parent_id).ok_or(GraphError::MissingParent(
+                    export_active_block.block.id,
+                    *parent_id,
+                ))?;
+                selected.insert(*parent_id);
+                to_visit.push(parent);
+            }
+        }
+
+        Ok(BootstrapableGraph {
+            final_blocks: self
+                .final_blocks
+                .iter()
+                .filter(|export_active_block| selected.contains(&export_active_block.block.id))
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// Checks that the total fitness of the final blocks does not exceed `max_total`,
+    /// which would indicate corrupted or forged bootstrap data.
+    pub fn validate_max_fitness(&self, max_total: u64) -> Result<(), GraphError> {
+        let mut total: u64 = 0;
+        for export_active_block in &self.final_blocks {
+            total = total
+                .checked_add(export_active_block.block.get_fitness())
+                .filter(|total| *total <= max_total)
+                .ok_or(GraphError::ExcessiveFitness(max_total))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the highest period among the final blocks of `thread`,
+    /// or `None` if the graph holds no block of that thread.
+    pub fn highest_period(&self, thread: u8) -> Option<u64> {
+        self.final_blocks
+            .iter()
+            .map(|export_active_block| export_active_block.block.content.header.content.slot)
+            .filter(|slot| slot.thread == thread)
+            .map(|slot| slot.period)
+            .max()
+    }
+
+    /// Checks that there is a genesis block (period 0) in each thread of `0..thread_count`.
+    ///
+    /// Reports all the threads without a genesis block.
+    pub fn validate_genesis_presence(&self, thread_count: u8) -> Result<(), GraphError> {
+        let mut has_genesis = vec![false; thread_count as usize];
+        for export_active_block in &self.final_blocks {
+            let slot = export_active_block.block.content.header.content.slot;
+            if slot.period == 0 {
+                if let Some(has_genesis) = has_genesis.get_mut(slot.thread as usize) {
+                    *has_genesis = true;
+                }
+            }
+        }
+        let missing: Vec<u8> = (0..thread_count)
+            .filter(|thread| !has_genesis[*thread as usize])
+            .collect();
+        if !missing.is_empty() {
+            return Err(GraphError::MissingGenesis(missing));
+        }
+        Ok(())
+    }
+
+    /// Checks that no two final blocks occupy the same slot.
+    ///
+    /// Reports the first collision found, in the order of `final_blocks`.
+    pub fn validate_unique_slots(&self) -> Result<(), GraphError> {
+        let mut slots: HashMap<Slot, BlockId> = HashMap::with_capacity(self.final_blocks.len());
+        for export_active_block in &self.final_blocks {
+            let slot = export_active_block.block.content.header.content.slot;
+            if let Some(other_id) = slots.insert(slot, export_active_block.block.id) {
+                return Err(GraphError::DuplicateSlot(
+                    slot,
+                    other_id,
+                    export_active_block.block.id,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that every parent of the final blocks is itself a final block of the graph.
+    ///
+    /// Reports the first missing parent found, in the order of `final_blocks`.
+    pub fn validate_parent_closure(&self) -> Result<(), GraphError> {
+        let ids: PreHashSet<BlockId> = self
+            .final_blocks
+            .iter()
+            .map(|export_active_block| export_active_block.block.id)
+            .collect();
+        for export_active_block in &self.final_blocks {
+            if let Some((parent_id, _)) = export_active_block
+                .parents
+                .iter()
+                .find(|(parent_id, _)| !ids.contains(parent_id))
+            {
+                return Err(GraphError::MissingParent(
+                    export_active_block.block.id,
+                    *parent_id,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the ids of the final blocks that are not a parent of any other final block,
+    /// i.e. the tips of the graph.
+    pub fn leaf_blocks(&self) -> PreHashSet<BlockId> {
+        let mut leaves: PreHashSet<BlockId> = self
+            .final_blocks
+            .iter()
+            .map(|export_active_block| export_active_block.block.id)
+            .collect();
+        for export_active_block in &self.final_blocks {
+            for (parent_id, _) in &export_active_block.parents {
+                leaves.remove(parent_id);
+            }
+        }
+        leaves
+    }
+
+    /// Returns the ids of the endorsements included in the final blocks,
+    /// to reconcile an endorsement pool with the finalized endorsements.
+    pub fn referenced_endorsement_ids(&self) -> PreHashSet<EndorsementId> {
+        self.final_blocks
+            .iter()
+            .flat_map(|export_active_block| export_active_block.endorsement_ids())
+            .collect()
+    }
+
+    /// Serializes the difference between `base` and this graph: the ids of the blocks of `base`
+    /// that are not in this graph, the blocks of both whose content differs, the blocks of this graph
+    /// that are not in `base`, then the order of the blocks of this graph.
+    ///
+    /// The order is written as the position of each block among the kept blocks of `base` followed by
+    /// the added blocks, or as an empty list if this graph already follows that order.
+    ///
+    /// See `BootstrapableGraphDeserializer::apply_delta` to rebuild this graph from `base`.
+    pub fn serialize_delta(
+        &self,
+        base: &BootstrapableGraph,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
+        let block_serializer = ExportActiveBlockSerializer::new();
+        let ids: PreHashSet<BlockId> = self.final_blocks.iter().map(|b| b.block.id).collect();
+        let base_blocks: PreHashMap<BlockId, &ExportActiveBlock> =
+            base.final_blocks.iter().map(|b| (b.block.id, b)).collect();
+        let removed: Vec<BlockId> = base
+            .final_blocks
+            .iter()
+            .map(|b| b.block.id)
+            .filter(|id| !ids.contains(id))
+            .collect();
+        let mut modified: Vec<&ExportActiveBlock> = Vec::new();
+        for export_active_block in &self.final_blocks {
+            if let Some(base_block) = base_blocks.get(&export_active_block.block.id) {
+                let mut serialized = Vec::new();
+                block_serializer.serialize(export_active_block, &mut serialized)?;
+                let mut base_serialized = Vec::new();
+                block_serializer.serialize(base_block, &mut base_serialized)?;
+                if serialized != base_serialized {
+                    modified.push(export_active_block);
+                }
+            }
+        }
+        let added: Vec<&ExportActiveBlock> = self
+            .final_blocks
+            .iter()
+            .filter(|b| !base_blocks.contains_key(&b.block.id))
+            .collect();
+
+        // position of each block of this graph in the order `apply_delta` rebuilds by default
+        let default_positions: PreHashMap<BlockId, u32> = base
+            .final_blocks
+            .iter()
+            .filter(|b| ids.contains(&b.block.id))
+            .chain(added.iter().copied())
+            .zip(0u32..)
+            .map(|(b, position)| (b.block.id, position))
+            .collect();
+        let order: Vec<u32> = self
+            .final_blocks
+            .iter()
+            .map(|b| default_positions[&b.block.id])
+            .collect();
+        let reordered = order
+            .iter()
+            .zip(0u32..)
+            .any(|(position, index)| *position != index);
+
+        let count_serializer = U32VarIntSerializer::new();
+        let count_error = |_| SerializeError::NumberTooBig("Too many blocks in delta".to_string());
+        count_serializer.serialize(&removed.len().try_into().map_err(count_error)?, buffer)?;
+        for id in removed {
+            buffer.extend(id.0.to_bytes());
+        }
+        count_serializer.serialize(&modified.len().try_into().map_err(count_error)?, buffer)?;
+        for export_active_block in modified {
+            block_serializer.serialize(export_active_block, buffer)?;
+        }
+        count_serializer.serialize(&added.len().try_into().map_err(count_error)?, buffer)?;
+        for export_active_block in added {
+            block_serializer.serialize(export_active_block, buffer)?;
+        }
+        if reordered {
+            count_serializer.serialize(&order.len().try_into().map_err(count_error)?, buffer)?;
+            for position in order {
+                count_serializer.serialize(&position, buffer)?;
+            }
+        } else {
+            count_serializer.serialize(&0, buffer)?;
+        }
+        Ok(())
+    }
+
+    /// Serializes the ranges `threads` and `periods`, then only the final blocks
+    /// whose slot is within both, so that a node missing a segment of the graph can sync just that segment.
+    ///
+    /// See `BootstrapableGraphDeserializer::deserialize_window`.
+    pub fn serialize_window(
+        &self,
+        threads: Range<u8>,
+        periods: Range<u64>,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
+        let window = GraphWindow { threads, periods };
+        let blocks: Vec<&ExportActiveBlock> = self
+            .final_blocks
+            .iter()
+            .filter(|b| window.contains(&b.block.content.header.content.slot))
+            .collect();
+
+        buffer.push(window.threads.start);
+        buffer.push(window.threads.end);
+        let period_serializer = U64VarIntSerializer::new();
+        period_serializer.serialize(&window.periods.start, buffer)?;
+        period_serializer.serialize(&window.periods.end, buffer)?;
+        U32VarIntSerializer::new().serialize(
+            &blocks.len().try_into().map_err(|_| {
+                SerializeError::NumberTooBig("Too many blocks in window".to_string())
+            })?,
+            buffer,
+        )?;
+        let block_serializer = ExportActiveBlockSerializer::new();
+        for export_active_block in blocks {
+            block_serializer.serialize(export_active_block, buffer)?;
+        }
+        Ok(())
+    }
+
+    fn merkle_leaves(&self) -> Result<Vec<Hash>, SerializeError> {
+        let serializer = ExportActiveBlockSerializer::new();
+        let mut buffer = Vec::new();
+        self.final_blocks
+            .iter()
+            .map(|export_active_block| {
+                buffer.clear();
+                serializer.serialize(export_active_block, &mut buffer)?;
+                Ok(leaf_hash(&buffer))
+            })
+            .collect()
+    }
+}
+
 /// Basic serializer for `BootstrapableGraph`
 #[derive(Default)]
 pub struct BootstrapableGraphSerializer {
     block_count_serializer: U32VarIntSerializer,
+    section_length_serializer: U64VarIntSerializer,
     export_active_block_serializer: ExportActiveBlockSerializer,
+    with_merkle_root: bool,
+    checksum: Option<ChecksumHash>,
+    max_final_blocks: Option<u32>,
 }
 
 impl BootstrapableGraphSerializer {
@@ -29,7 +707,320 @@ impl BootstrapableGraphSerializer {
     pub fn new() -> Self {
         Self {
             block_count_serializer: U32VarIntSerializer::new(),
+            section_length_serializer: U64VarIntSerializer::new(),
             export_active_block_serializer: ExportActiveBlockSerializer::new(),
+            with_merkle_root: false,
+            checksum: None,
+            max_final_blocks: None,
+        }
+    }
+
+    /// Serializes the graph in two sections: first the headers of all the final blocks
+    /// (with their parents and finality), prefixed by the length of that section,
+    /// then the operation ids of all the blocks.
+    ///
+    /// This lets a receiver parse and check the header chain before parsing the bodies,
+    /// see `BootstrapableGraphDeserializer::deserialize_header_section`.
+    pub fn serialize_headers_first(
+        &self,
+        value: &BootstrapableGraph,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
+        // block count
+        self.block_count_serializer
+            .serialize(&self.block_count(value)?, buffer)?;
+
+        // header section
+        let mut header_section = Vec::new();
+        for export_active_block in &value.final_blocks {
+            self.export_active_block_serializer
+                .serialize_header(export_active_block, &mut header_section)?;
+        }
+        self.section_length_serializer
+            .serialize(&(header_section.len() as u64), buffer)?;
+        buffer.extend(header_section);
+
+        // body section
+        for export_active_block in &value.final_blocks {
+            self.export_active_block_serializer
+                .serialize_body(export_active_block, buffer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the graph like `serialize`, calling `progress(done, total)` after each final block is written,
+    /// with `done` the number of blocks written so far and `total` the number of final blocks.
+    ///
+    /// Meant to report progress when writing a large graph, e.g. to disk.
+    pub fn serialize_with_progress<F>(
+        &self,
+        value: &BootstrapableGraph,
+        buffer: &mut Vec<u8>,
+        mut progress: F,
+    ) -> Result<(), SerializeError>
+    where
+        F: FnMut(usize, usize),
+    {
+        self.serialize_with_hook(value, buffer, |done, total, _| {
+            progress(done, total);
+            Ok(())
+        })
+    }
+
+    /// Serializes the graph like `serialize`, failing with `SerializeError::TooLarge` as soon as
+    /// more than `max_bytes` bytes are written.
+    ///
+    /// The size is checked after each final block, so that an oversized graph is not serialized entirely.
+    /// On failure, `buffer` is left as it was before the call.
+    pub fn serialize_bounded(
+        &self,
+        value: &BootstrapableGraph,
+        buffer: &mut Vec<u8>,
+        max_bytes: usize,
+    ) -> Result<(), SerializeError> {
+        let start = buffer.len();
+        let too_large = |written: usize, done: usize| {
+            SerializeError::TooLarge(format!(
+                "{} bytes written after {} final blocks, above the maximum of {} bytes",
+                written, done, max_bytes
+            ))
+        };
+        let result = self
+            .serialize_with_hook(value, buffer, |done, _, written| {
+                if written > max_bytes {
+                    return Err(too_large(written, done));
+                }
+                Ok(())
+            })
+            .and_then(|_| {
+                let written = buffer.len() - start;
+                if written > max_bytes {
+                    return Err(too_large(written, value.final_blocks.len()));
+                }
+                Ok(())
+            });
+        if result.is_err() {
+            buffer.truncate(start);
+        }
+        result
+    }
+
+    /// Serializes the graph, calling `hook(done, total, written)` after each final block is written,
+    /// with `written` the number of bytes written so far by this call. Stops at the first error of `hook`.
+    fn serialize_with_hook<F>(
+        &self,
+        value: &BootstrapableGraph,
+        buffer: &mut Vec<u8>,
+        mut hook: F,
+    ) -> Result<(), SerializeError>
+    where
+        F: FnMut(usize, usize, usize) -> Result<(), SerializeError>,
+    {
+        let start = buffer.len();
+
+        // fail before writing anything if there are too many final blocks
+        self.block_count(value)?;
+
+        if !self.with_merkle_root && self.checksum.is_none() {
+            return self.serialize_final_blocks(value, buffer, |done, total, buffer_len| {
+                hook(done, total, buffer_len - start)
+            });
+        }
+
+        // the merkle root and the checksum precede the final blocks but depend on them:
+        // the blocks are written aside first, hashed one by one and checked by `hook` as they are written
+        let merkle_root_len = if self.with_merkle_root {
+            HASH_SIZE_BYTES
+        } else {
+            0
+        };
+        let checksum_len = self
+            .checksum
+            .map_or(0, |checksum| 1 + checksum.digest_len());
+        let prefix_len = merkle_root_len + checksum_len;
+        let mut blocks = Vec::new();
+        self.block_count_serializer
+            .serialize(&self.block_count(value)?, &mut blocks)?;
+        let total = value.final_blocks.len();
+        let mut leaves = Vec::new();
+        for (index, export_active_block) in value.final_blocks.iter().enumerate() {
+            let block_start = blocks.len();
+            self.export_active_block_serializer
+                .serialize(export_active_block, &mut blocks)?;
+            if self.with_merkle_root {
+                leaves.push(leaf_hash(&blocks[block_start..]));
+            }
+            hook(index + 1, total, prefix_len + blocks.len())?;
+        }
+
+        // merkle root
+        if self.with_merkle_root {
+            buffer.extend(compute_root(leaves).to_bytes());
+        }
+
+        // checksum header byte and checksum of the final blocks
+        if let Some(checksum) = self.checksum {
+            buffer.push(checksum.to_byte());
+            buffer.extend(checksum.digest(&blocks));
+        }
+        buffer.extend(blocks);
+        Ok(())
+    }
+
+    /// Writes the block count and the final blocks, calling `hook(done, total, buffer.len())` after each block
+    fn serialize_final_blocks<F>(
+        &self,
+        value: &BootstrapableGraph,
+        buffer: &mut Vec<u8>,
+        mut hook: F,
+    ) -> Result<(), SerializeError>
+    where
+        F: FnMut(usize, usize, usize) -> Result<(), SerializeError>,
+    {
+        // block count
+        self.block_count_serializer
+            .serialize(&self.block_count(value)?, buffer)?;
+
+        // final blocks
+        let total = value.final_blocks.len();
+        for (index, export_active_block) in value.final_blocks.iter().enumerate() {
+            self.export_active_block_serializer
+                .serialize(export_active_block, buffer)?;
+            hook(index + 1, total, buffer.len())?;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the graph like `serialize` into a new buffer, along with the labeled byte range
+    /// of each part of it: "merkle root", "checksum hash", "checksum", "block count", then "block[i].header",
+    /// "block[i].operations", "block[i].parents" and "block[i].is_final" for each final block.
+    ///
+    /// Meant to debug format mismatches: the ranges follow each other and cover the whole buffer.
+    pub fn serialize_annotated(
+        &self,
+        value: &BootstrapableGraph,
+    ) -> Result<(Vec<u8>, Vec<(String, Range<usize>)>), SerializeError> {
+        let mut buffer = Vec::new();
+        let mut annotations = Vec::new();
+        let mut annotate = |label: &str, buffer: &[u8], start: usize| {
+            annotations.push((label.to_string(), start..buffer.len()));
+        };
+
+        // merkle root
+        if self.with_merkle_root {
+            buffer.extend(value.merkle_root()?.to_bytes());
+            annotate("merkle root", &buffer, 0);
+        }
+
+        // block count and final blocks, that the checksum is computed on
+        let mut blocks = Vec::new();
+        self.block_count_serializer
+            .serialize(&self.block_count(value)?, &mut blocks)?;
+        let mut block_annotations = vec![("block count".to_string(), 0..blocks.len())];
+        for (index, export_active_block) in value.final_blocks.iter().enumerate() {
+            self.export_active_block_serializer.serialize_annotated(
+                export_active_block,
+                &format!("block[{}]", index),
+                &mut blocks,
+                &mut block_annotations,
+            )?;
+        }
+
+        // checksum header byte and checksum
+        if let Some(checksum) = self.checksum {
+            let start = buffer.len();
+            buffer.push(checksum.to_byte());
+            annotate("checksum hash", &buffer, start);
+            let start = buffer.len();
+            buffer.extend(checksum.digest(&blocks));
+            annotate("checksum", &buffer, start);
+        }
+
+        let offset = buffer.len();
+        buffer.extend(blocks);
+        annotations.extend(
+            block_annotations
+                .into_iter()
+                .map(|(label, range)| (label, range.start + offset..range.end + offset)),
+        );
+        Ok((buffer, annotations))
+    }
+
+    /// Returns the number of final blocks, failing if it exceeds `max_final_blocks` or `u32::MAX`
+    fn block_count(&self, value: &BootstrapableGraph) -> Result<u32, SerializeError> {
+        let count = value.final_blocks.len();
+        if let Some(max_final_blocks) = self.max_final_blocks {
+            if count > max_final_blocks as usize {
+                return Err(SerializeError::NumberTooBig(format!(
+                    "{} final blocks, above the maximum of {}",
+                    count, max_final_blocks
+                )));
+            }
+        }
+        count
+            .try_into()
+            .map_err(|_| SerializeError::NumberTooBig("Too many final blocks".to_string()))
+    }
+
+    /// Serializes the graph with each final block compressed independently with DEFLATE
+    /// and prefixed by its compressed length.
+    ///
+    /// Returns the offset in `buffer` of each compressed block, so that a single block can be
+    /// decompressed with `BootstrapableGraphDeserializer::deserialize_compressed_block`
+    /// without decompressing its neighbors.
+    pub fn serialize_compressed_blocks(
+        &self,
+        value: &BootstrapableGraph,
+        buffer: &mut Vec<u8>,
+    ) -> Result<Vec<usize>, SerializeError> {
+        // block count
+        self.block_count_serializer
+            .serialize(&self.block_count(value)?, buffer)?;
+
+        // compressed final blocks
+        let mut offsets = Vec::with_capacity(value.final_blocks.len());
+        let mut serialized_block = Vec::new();
+        for export_active_block in &value.final_blocks {
+            serialized_block.clear();
+            self.export_active_block_serializer
+                .serialize(export_active_block, &mut serialized_block)?;
+            let compressed_block = compress_block(&serialized_block)?;
+            offsets.push(buffer.len());
+            self.section_length_serializer
+                .serialize(&(compressed_block.len() as u64), buffer)?;
+            buffer.extend(compressed_block);
+        }
+
+        Ok(offsets)
+    }
+
+    /// Creates a `BootstrapableGraphSerializer` that writes the Merkle root of the final blocks
+    /// before them, see `BootstrapableGraph::merkle_root`
+    pub fn new_with_merkle_root() -> Self {
+        Self {
+            with_merkle_root: true,
+            ..Self::new()
+        }
+    }
+
+    /// Creates a `BootstrapableGraphSerializer` that writes an integrity checksum of the final blocks
+    /// computed with `checksum`, see `ChecksumHash`
+    pub fn new_with_checksum(checksum: ChecksumHash) -> Self {
+        Self {
+            checksum: Some(checksum),
+            ..Self::new()
+        }
+    }
+
+    /// Creates a `BootstrapableGraphSerializer` that fails before writing anything
+    /// if the graph has more than `max_final_blocks` final blocks,
+    /// e.g. to enforce the bound of the receiving `BootstrapableGraphDeserializer`
+    pub fn new_with_max_final_blocks(max_final_blocks: u32) -> Self {
+        Self {
+            max_final_blocks: Some(max_final_blocks),
+            ..Self::new()
         }
     }
 }
@@ -52,30 +1043,112 @@ impl Serializer<BootstrapableGraph> for BootstrapableGraphSerializer {
         value: &BootstrapableGraph,
         buffer: &mut Vec<u8>,
     ) -> Result<(), SerializeError> {
-        // block count
-        self.block_count_serializer.serialize(
-            &value
-                .final_blocks
-                .len()
-                .try_into()
-                .map_err(|_| SerializeError::NumberTooBig("Too many final blocks".to_string()))?,
-            buffer,
-        )?;
+        self.serialize_with_progress(value, buffer, |_, _| {})
+    }
+}
 
-        // final blocks
-        for export_active_block in &value.final_blocks {
-            self.export_active_block_serializer
-                .serialize(export_active_block, buffer)?;
+/// Compresses a serialized block with DEFLATE
+fn compress_block(serialized_block: &[u8]) -> Result<Vec<u8>, SerializeError> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(serialized_block)
+        .map_err(|err| SerializeError::GeneralError(err.to_string()))?;
+    encoder
+        .finish()
+        .map_err(|err| SerializeError::GeneralError(err.to_string()))
+}
+
+/// Serializer for an optional `BootstrapableGraph`.
+///
+/// `None` is written as a one-byte sentinel meaning that no graph is available,
+/// which is distinct from an empty graph.
+pub type OptionalBootstrapableGraphSerializer =
+    OptionSerializer<BootstrapableGraph, BootstrapableGraphSerializer>;
+
+/// Bounds applied when deserializing a `BootstrapableGraph`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootstrapLimits {
+    /// number of threads
+    pub thread_count: u8,
+    /// maximum number of final blocks
+    pub max_bootstrap_blocks: u32,
+    /// maximum number of operations in a block
+    pub max_operations_per_block: u32,
+    /// number of endorsements in a block
+    pub endorsement_count: u32,
+    /// maximum number of denunciations in a block header
+    pub max_denunciations_per_block_header: u32,
+    /// if Some(lsp), blocks with a period before `lsp` are rejected
+    pub last_start_period: Option<u64>,
+    /// maximum number of cliques sent along with the graph
+    pub max_cliques: u32,
+    /// maximum size in bytes of the serialized final blocks
+    pub max_bytes: u64,
+    /// maximum number of operations over all the final blocks
+    pub max_operations: u64,
+    /// maximum number of periods between the oldest and the newest final block
+    pub max_depth: u64,
+    /// if true, blocks failing `ExportActiveBlock::validate_thread_consistency` are rejected
+    pub validate_thread_consistency: bool,
+}
+
+impl Default for BootstrapLimits {
+    fn default() -> Self {
+        Self {
+            thread_count: THREAD_COUNT,
+            max_bootstrap_blocks: MAX_BOOTSTRAP_BLOCKS,
+            max_operations_per_block: MAX_OPERATIONS_PER_BLOCK,
+            endorsement_count: ENDORSEMENT_COUNT,
+            max_denunciations_per_block_header: MAX_DENUNCIATIONS_PER_BLOCK_HEADER,
+            last_start_period: None,
+            max_cliques: MAX_BOOTSTRAP_CLIQUES,
+            max_bytes: MAX_BOOTSTRAP_MESSAGE_SIZE as u64,
+            max_operations: MAX_BOOTSTRAP_BLOCKS as u64 * MAX_OPERATIONS_PER_BLOCK as u64,
+            max_depth: POS_SAVED_CYCLES as u64 * PERIODS_PER_CYCLE,
+            validate_thread_consistency: false,
+        }
+    }
+}
+
+impl BootstrapLimits {
+    /// Arguments of the deserializer of the final blocks
+    pub fn block_deserializer_args(&self) -> BlockDeserializerArgs {
+        BlockDeserializerArgs {
+            thread_count: self.thread_count,
+            max_operations_per_block: self.max_operations_per_block,
+            endorsement_count: self.endorsement_count,
+            max_denunciations_per_block_header: self.max_denunciations_per_block_header,
+            last_start_period: self.last_start_period,
         }
+    }
 
-        Ok(())
+    /// Deserializer of the cliques sent along with the graph
+    pub fn cliques_deserializer(&self) -> CliquesDeserializer {
+        CliquesDeserializer::new(self.max_cliques, self.max_bootstrap_blocks)
     }
 }
 
+/// Totals of the final blocks deserialized so far,
+/// checked by `BootstrapableGraphDeserializer::check_budget`
+#[derive(Debug, Default)]
+struct BlockBudget {
+    bytes: u64,
+    operations: u64,
+    periods: Option<(u64, u64)>,
+}
+
 /// Basic deserializer for `BootstrapableGraph`
 pub struct BootstrapableGraphDeserializer {
     block_count_deserializer: U32VarIntDeserializer,
+    section_length_deserializer: U64VarIntDeserializer,
+    compressed_block_length_deserializer: U64VarIntDeserializer,
     export_active_block_deserializer: ExportActiveBlockDeserializer,
+    hash_deserializer: HashDeserializer,
+    with_merkle_root: bool,
+    with_checksum: bool,
+    max_bytes: u64,
+    max_operations: u64,
+    max_depth: u64,
 }
 
 impl BootstrapableGraphDeserializer {
@@ -87,7 +1160,642 @@ impl BootstrapableGraphDeserializer {
                 Included(0),
                 Included(max_bootstrap_blocks),
             ),
+            section_length_deserializer: U64VarIntDeserializer::new(
+                Included(0),
+                Included(u64::MAX),
+            ),
+            compressed_block_length_deserializer: U64VarIntDeserializer::new(
+                Included(0),
+                Included(MAX_BLOCK_SIZE as u64),
+            ),
             export_active_block_deserializer: ExportActiveBlockDeserializer::new(block_der_args),
+            hash_deserializer: HashDeserializer::new(),
+            with_merkle_root: false,
+            with_checksum: false,
+            max_bytes: u64::MAX,
+            max_operations: u64::MAX,
+            max_depth: u64::MAX,
+        }
+    }
+
+    /// Creates a `BootstrapableGraphDeserializer` bounded by `limits`.
+    ///
+    /// The byte, operation and depth budgets are checked block by block by every
+    /// deserialization method, so that a graph exceeding them is rejected
+    /// before it is fully deserialized.
+    pub fn with_limits(limits: BootstrapLimits) -> Self {
+        let export_active_block_deserializer = if limits.validate_thread_consistency {
+            ExportActiveBlockDeserializer::new_with_thread_validation(
+                limits.block_deserializer_args(),
+            )
+        } else {
+            ExportActiveBlockDeserializer::new(limits.block_deserializer_args())
+        };
+        Self {
+            export_active_block_deserializer,
+            max_bytes: limits.max_bytes,
+            max_operations: limits.max_operations,
+            max_depth: limits.max_depth,
+            ..Self::new(
+                limits.block_deserializer_args(),
+                limits.max_bootstrap_blocks,
+            )
+        }
+    }
+
+    /// Creates a `BootstrapableGraphDeserializer` for graphs serialized with their Merkle root.
+    /// The root is checked against the deserialized blocks.
+    pub fn new_with_merkle_root(
+        block_der_args: BlockDeserializerArgs,
+        max_bootstrap_blocks: u32,
+    ) -> Self {
+        Self {
+            with_merkle_root: true,
+            ..Self::new(block_der_args, max_bootstrap_blocks)
+        }
+    }
+
+    /// Creates a `BootstrapableGraphDeserializer` for graphs serialized with an integrity checksum.
+    /// The checksum is verified with the hash function recorded in its header byte.
+    pub fn new_with_checksum(
+        block_der_args: BlockDeserializerArgs,
+        max_bootstrap_blocks: u32,
+    ) -> Self {
+        Self {
+            with_checksum: true,
+            ..Self::new(block_der_args, max_bootstrap_blocks)
+        }
+    }
+
+    /// Deserializes the block count and the header section of a graph written by
+    /// `BootstrapableGraphSerializer::serialize_headers_first`.
+    ///
+    /// The returned rest starts with the body section.
+    pub fn deserialize_header_section<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], Vec<ExportActiveBlockHeader>, E> {
+        self.deserialize_sized_header_section(buffer)
+            .map(|(rest, headers)| (rest, headers.into_iter().map(|(_, h)| h).collect()))
+    }
+
+    /// Same as `deserialize_header_section`, along with the serialized size of each header
+    fn deserialize_sized_header_section<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], Vec<(usize, ExportActiveBlockHeader)>, E> {
+        let (rest, block_count) = context("Failed final block count deserialization", |input| {
+            self.block_count_deserializer.deserialize(input)
+        })
+        .parse(buffer)?;
+        let (rest, header_section) = context("Failed header section deserialization", |input| {
+            let (rest, length) = self.section_length_deserializer.deserialize(input)?;
+            let length = usize::try_from(length).map_err(|_| {
+                nom::Err::Error(ParseError::from_error_kind(input, ErrorKind::TooLarge))
+            })?;
+            take(length)(rest)
+        })
+        .parse(rest)?;
+        let (_, headers) = context(
+            "Failed block headers deserialization",
+            all_consuming(count(
+                consumed(|input| {
+                    self.export_active_block_deserializer
+                        .deserialize_header(input)
+                })
+                .map(|(serialized_header, header)| (serialized_header.len(), header)),
+                block_count as usize,
+            )),
+        )
+        .parse(header_section)?;
+        Ok((rest, headers))
+    }
+
+    /// Deserializes a graph written by `BootstrapableGraphSerializer::serialize_headers_first`
+    pub fn deserialize_headers_first<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], BootstrapableGraph, E> {
+        let (mut rest, headers) = self.deserialize_sized_header_section(buffer)?;
+        let mut final_blocks = Vec::with_capacity(headers.len());
+        let mut budget = BlockBudget::default();
+        for (header_size, header) in headers {
+            let (body_rest, operations) = self
+                .export_active_block_deserializer
+                .deserialize_body(rest)?;
+            let export_active_block =
+                header.into_export_active_block(operations).map_err(|_| {
+                    nom::Err::Failure(ContextError::add_context(
+                        rest,
+                        "Failed block reconstruction from its header and body",
+                        ParseError::from_error_kind(rest, ErrorKind::Fail),
+                    ))
+                })?;
+            self.check_budget(
+                &mut budget,
+                &export_active_block,
+                header_size + rest.len() - body_rest.len(),
+            )
+            .map_err(|message| budget_exceeded(rest, message))?;
+            final_blocks.push(export_active_block);
+            rest = body_rest;
+        }
+        Ok((rest, BootstrapableGraph { final_blocks }))
+    }
+
+    /// Deserializes a graph into a shared slice of its final blocks,
+    /// that can be handed to several subsystems without cloning the blocks.
+    pub fn deserialize_arc<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], Arc<[ExportActiveBlock]>, E> {
+        self.deserialize(buffer)
+            .map(|(rest, graph)| (rest, graph.final_blocks.into()))
+    }
+
+    /// Deserializes a graph written by `BootstrapableGraphSerializer::serialize_compressed_blocks`
+    pub fn deserialize_compressed_blocks<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], BootstrapableGraph, E> {
+        let mut budget = BlockBudget::default();
+        context(
+            "Failed compressed BootstrapableGraph deserialization",
+            length_count(
+                context("Failed final block count deserialization", |input| {
+                    self.block_count_deserializer.deserialize(input)
+                }),
+                |input: &'a [u8]| {
+                    let (rest, export_active_block) = self.deserialize_compressed_block(input)?;
+                    self.check_budget(&mut budget, &export_active_block, input.len() - rest.len())
+                        .map_err(|message| budget_exceeded(input, message))?;
+                    Ok((rest, export_active_block))
+                },
+            ),
+        )
+        .map(|final_blocks| BootstrapableGraph { final_blocks })
+        .parse(buffer)
+    }
+
+    /// Decompresses and deserializes a single block written by
+    /// `BootstrapableGraphSerializer::serialize_compressed_blocks`, starting at its offset.
+    ///
+    /// Decompressed blocks larger than `MAX_BLOCK_SIZE` are rejected.
+    pub fn deserialize_compressed_block<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], ExportActiveBlock, E> {
+        context(
+            "Failed compressed block deserialization",
+            |input: &'a [u8]| {
+                let (rest, compressed_block) = length_data(|input| {
+                    self.compressed_block_length_deserializer.deserialize(input)
+                })(input)?;
+                let export_active_block =
+                    self.decompress_block(compressed_block).map_err(|message| {
+                        nom::Err::Failure(ContextError::add_context(
+                            input,
+                            message,
+                            ParseError::from_error_kind(input, ErrorKind::Verify),
+                        ))
+                    })?;
+                Ok((rest, export_active_block))
+            },
+        )
+        .parse(buffer)
+    }
+
+    /// Deserializes a graph written by `BootstrapableGraphSerializer::serialize_compressed_blocks`,
+    /// decompressing and parsing its blocks in parallel on the rayon thread pool.
+    ///
+    /// The compressed blocks are located sequentially from their length prefixes,
+    /// then decoded independently and assembled in their original order.
+    pub fn deserialize_parallel<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], BootstrapableGraph, E> {
+        let (rest, compressed_blocks) = context(
+            "Failed compressed BootstrapableGraph deserialization",
+            length_count(
+                context("Failed final block count deserialization", |input| {
+                    self.block_count_deserializer.deserialize(input)
+                }),
+                context("Failed compressed block deserialization", |input| {
+                    consumed(length_data(|input| {
+                        self.compressed_block_length_deserializer.deserialize(input)
+                    }))(input)
+                }),
+            ),
+        )
+        .parse(buffer)?;
+
+        // each entry holds the framed compressed block, to locate errors, and its DEFLATE data
+        let decoded: Vec<Result<ExportActiveBlock, &'static str>> = compressed_blocks
+            .par_iter()
+            .map(|(_, compressed_block)| self.decompress_block(compressed_block))
+            .collect();
+        let mut budget = BlockBudget::default();
+        let final_blocks = decoded
+            .into_iter()
+            .zip(&compressed_blocks)
+            .map(|(export_active_block, (input, _))| {
+                let export_active_block = export_active_block.map_err(|message| {
+                    nom::Err::Failure(ContextError::add_context(
+                        *input,
+                        message,
+                        ParseError::from_error_kind(*input, ErrorKind::Verify),
+                    ))
+                })?;
+                self.check_budget(&mut budget, &export_active_block, input.len())
+                    .map_err(|message| budget_exceeded(*input, message))?;
+                Ok(export_active_block)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((rest, BootstrapableGraph { final_blocks }))
+    }
+
+    /// Decompresses and deserializes the DEFLATE data of a single block.
+    ///
+    /// Decompressed blocks larger than `MAX_BLOCK_SIZE` are rejected.
+    /// On failure, returns the context message to report.
+    fn decompress_block(&self, compressed_block: &[u8]) -> Result<ExportActiveBlock, &'static str> {
+        let mut serialized_block = Vec::new();
+        DeflateDecoder::new(compressed_block)
+            .take(MAX_BLOCK_SIZE as u64 + 1)
+            .read_to_end(&mut serialized_block)
+            .map_err(|_| "Failed block decompression")?;
+        if serialized_block.len() > MAX_BLOCK_SIZE as usize {
+            return Err("Decompressed block too large");
+        }
+
+        let (_, export_active_block) = all_consuming(|input| {
+            self.export_active_block_deserializer
+                .deserialize::<DeserializeError>(input)
+        })(&serialized_block)
+        .map_err(|_| "Failed decompressed block deserialization")?;
+        Ok(export_active_block)
+    }
+
+    /// Deserializes a graph written by `BootstrapableGraphSerializer::serialize` from `reader`,
+    /// calling `on_block` with each final block as soon as it is parsed.
+    ///
+    /// Only the bytes of the block being parsed are buffered, so that a large snapshot
+    /// does not have to be loaded in memory, and a block that cannot be parsed from
+    /// `MAX_BLOCK_SIZE` bytes is rejected. Returns the number of final blocks.
+    /// Graphs serialized with a Merkle root or a checksum are not supported.
+    pub fn deserialize_from_bufread<R: BufRead, F: FnMut(ExportActiveBlock)>(
+        &self,
+        mut reader: R,
+        mut on_block: F,
+    ) -> Result<u32, GraphError> {
+        if self.with_merkle_root || self.with_checksum {
+            return Err(GraphError::InvalidStream(
+                "Merkle root and checksum headers cannot be streamed".to_string(),
+            ));
+        }
+        let mut buffer = Vec::new();
+        let mut position = 0;
+        let block_count = read_parsed(
+            &mut reader,
+            &mut buffer,
+            &mut position,
+            U32_VARINT_MAX_SIZE,
+            |input| {
+                context("Failed final block count deserialization", |input| {
+                    self.block_count_deserializer.deserialize(input)
+                })
+                .parse(input)
+            },
+        )?;
+        // the remaining data of a stream is only bounded by the byte budget
+        if (block_count as u64).saturating_mul(MIN_EXPORT_ACTIVE_BLOCK_SIZE as u64) > self.max_bytes
+        {
+            return Err(GraphError::InvalidStream(
+                "Final block count too large for the byte budget".to_string(),
+            ));
+        }
+        let mut budget = BlockBudget::default();
+        for _ in 0..block_count {
+            let (size, export_active_block) = read_parsed(
+                &mut reader,
+                &mut buffer,
+                &mut position,
+                MAX_BLOCK_SIZE as usize,
+                |input| {
+                    consumed(context(
+                        "Failed export_active_block deserialization",
+                        |input| self.export_active_block_deserializer.deserialize(input),
+                    ))
+                    .map(|(serialized_block, block)| (serialized_block.len(), block))
+                    .parse(input)
+                },
+            )?;
+            self.check_budget(&mut budget, &export_active_block, size)
+                .map_err(|message| GraphError::InvalidStream(message.to_string()))?;
+            on_block(export_active_block);
+        }
+        if position < buffer.len() || !reader.fill_buf()?.is_empty() {
+            return Err(GraphError::InvalidStream(
+                "trailing data after the final blocks".to_string(),
+            ));
+        }
+        Ok(block_count)
+    }
+
+    /// Rebuilds a graph from `base` and a delta written by `BootstrapableGraph::serialize_delta`.
+    ///
+    /// The blocks of `base` that were not removed are replaced by their modified version if any,
+    /// followed by the added blocks, then put back in the order recorded in the delta.
+    pub fn apply_delta(
+        &self,
+        base: &BootstrapableGraph,
+        delta: &[u8],
+    ) -> Result<BootstrapableGraph, GraphError> {
+        let (_, (removed, modified, added, order)) =
+            all_consuming(|input| self.deserialize_delta(input))(delta).map_err(
+                |err: nom::Err<DeserializeError>| GraphError::InvalidDelta(err.to_string()),
+            )?;
+        let mut budget = BlockBudget::default();
+        for (serialized_block, export_active_block) in modified.iter().chain(&added) {
+            self.check_budget(&mut budget, export_active_block, serialized_block.len())
+                .map_err(|message| GraphError::InvalidDelta(message.to_string()))?;
+        }
+        let added: Vec<ExportActiveBlock> = added.into_iter().map(|(_, b)| b).collect();
+
+        let mut removed: PreHashSet<BlockId> = removed.into_iter().collect();
+        let mut modified: PreHashMap<BlockId, ExportActiveBlock> =
+            modified.into_iter().map(|(_, b)| (b.block.id, b)).collect();
+        let mut final_blocks = Vec::with_capacity(base.final_blocks.len() + added.len());
+        for export_active_block in &base.final_blocks {
+            let id = export_active_block.block.id;
+            if removed.remove(&id) {
+                continue;
+            }
+            match modified.remove(&id) {
+                Some(modified_block) => final_blocks.push(modified_block),
+                None => final_blocks.push(export_active_block.clone()),
+            }
+        }
+        if let Some(id) = removed.into_iter().next() {
+            return Err(GraphError::InvalidDelta(format!(
+                "removed block {} is not in the base graph",
+                id
+            )));
+        }
+        if let Some(id) = modified.into_keys().next() {
+            return Err(GraphError::InvalidDelta(format!(
+                "modified block {} is not kept from the base graph",
+                id
+            )));
+        }
+        let base_ids: PreHashSet<BlockId> = base.final_blocks.iter().map(|b| b.block.id).collect();
+        if let Some(export_active_block) = added.iter().find(|b| base_ids.contains(&b.block.id)) {
+            return Err(GraphError::InvalidDelta(format!(
+                "added block {} is already in the base graph",
+                export_active_block.block.id
+            )));
+        }
+        final_blocks.extend(added);
+
+        if order.is_empty() {
+            return Ok(BootstrapableGraph { final_blocks });
+        }
+        if order.len() != final_blocks.len() {
+            return Err(GraphError::InvalidDelta(format!(
+                "order of {} blocks for a graph of {} blocks",
+                order.len(),
+                final_blocks.len()
+            )));
+        }
+        let mut blocks: Vec<Option<ExportActiveBlock>> =
+            final_blocks.into_iter().map(Some).collect();
+        let final_blocks = order
+            .into_iter()
+            .map(|position| {
+                blocks
+                    .get_mut(position as usize)
+                    .and_then(Option::take)
+                    .ok_or_else(|| {
+                        GraphError::InvalidDelta(format!(
+                            "order position {} is out of range or repeated",
+                            position
+                        ))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(BootstrapableGraph { final_blocks })
+    }
+
+    /// Deserializes a graph written by `BootstrapableGraph::serialize_window`, along with its window.
+    ///
+    /// Blocks outside of the window are rejected.
+    pub fn deserialize_window<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], (GraphWindow, BootstrapableGraph), E> {
+        context(
+            "Failed graph window deserialization",
+            |input: &'a [u8]| {
+                let (rest, (thread_start, thread_end, period_start, period_end)) = tuple((
+                    context(
+                        "Failed thread range deserialization",
+                        nom::number::complete::u8,
+                    ),
+                    context(
+                        "Failed thread range deserialization",
+                        nom::number::complete::u8,
+                    ),
+                    context("Failed period range deserialization", |input| {
+                        self.section_length_deserializer.deserialize(input)
+                    }),
+                    context("Failed period range deserialization", |input| {
+                        self.section_length_deserializer.deserialize(input)
+                    }),
+                ))
+                .parse(input)?;
+                let window = GraphWindow {
+                    threads: thread_start..thread_end,
+                    periods: period_start..period_end,
+                };
+                let mut budget = BlockBudget::default();
+                let (rest, final_blocks) = length_count(
+                    context("Failed final block count deserialization", |input| {
+                        self.block_count_deserializer.deserialize(input)
+                    }),
+                    context(
+                        "Failed export_active_block deserialization",
+                        |input: &'a [u8]| {
+                            let (rest, export_active_block) =
+                                self.export_active_block_deserializer.deserialize(input)?;
+                            self.check_budget(
+                                &mut budget,
+                                &export_active_block,
+                                input.len() - rest.len(),
+                            )
+                            .map_err(|message| budget_exceeded(input, message))?;
+                            if !window
+                                .contains(&export_active_block.block.content.header.content.slot)
+                            {
+                                return Err(nom::Err::Failure(ContextError::add_context(
+                                    input,
+                                    "Block outside of the window",
+                                    ParseError::from_error_kind(input, ErrorKind::Verify),
+                                )));
+                            }
+                            Ok((rest, export_active_block))
+                        },
+                    ),
+                )
+                .parse(rest)?;
+                Ok((rest, (window, BootstrapableGraph { final_blocks })))
+            },
+        )
+        .parse(buffer)
+    }
+
+    /// Accounts for a final block read from `size` bytes in `budget`, then checks
+    /// the byte, operation and depth budgets. On failure, returns the context message to report.
+    fn check_budget(
+        &self,
+        budget: &mut BlockBudget,
+        export_active_block: &ExportActiveBlock,
+        size: usize,
+    ) -> Result<(), &'static str> {
+        budget.bytes = budget.bytes.saturating_add(size as u64);
+        if budget.bytes > self.max_bytes {
+            return Err("Final blocks exceed the byte budget");
+        }
+        budget.operations = budget
+            .operations
+            .saturating_add(export_active_block.block.content.operations.len() as u64);
+        if budget.operations > self.max_operations {
+            return Err("Final blocks exceed the operation budget");
+        }
+        let period = export_active_block.block.content.header.content.slot.period;
+        let (oldest, newest) = budget
+            .periods
+            .map_or((period, period), |(o, n)| (o.min(period), n.max(period)));
+        if newest - oldest > self.max_depth {
+            return Err("Final blocks exceed the depth limit");
+        }
+        budget.periods = Some((oldest, newest));
+        Ok(())
+    }
+
+    fn deserialize_delta<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<
+        &'a [u8],
+        (
+            Vec<BlockId>,
+            Vec<(&'a [u8], ExportActiveBlock)>,
+            Vec<(&'a [u8], ExportActiveBlock)>,
+            Vec<u32>,
+        ),
+        E,
+    > {
+        context(
+            "Failed graph delta deserialization",
+            tuple((
+                length_count(
+                    context("Failed removed block count deserialization", |input| {
+                        self.block_count_deserializer.deserialize(input)
+                    }),
+                    context("Failed removed block id deserialization", |input| {
+                        self.hash_deserializer
+                            .deserialize(input)
+                            .map(|(rest, hash)| (rest, BlockId(hash)))
+                    }),
+                ),
+                length_count(
+                    context("Failed modified block count deserialization", |input| {
+                        self.block_count_deserializer.deserialize(input)
+                    }),
+                    context(
+                        "Failed modified block deserialization",
+                        consumed(|input| self.export_active_block_deserializer.deserialize(input)),
+                    ),
+                ),
+                length_count(
+                    context("Failed added block count deserialization", |input| {
+                        self.block_count_deserializer.deserialize(input)
+                    }),
+                    context(
+                        "Failed added block deserialization",
+                        consumed(|input| self.export_active_block_deserializer.deserialize(input)),
+                    ),
+                ),
+                length_count(
+                    context("Failed block order count deserialization", |input| {
+                        self.block_count_deserializer.deserialize(input)
+                    }),
+                    context("Failed block order position deserialization", |input| {
+                        self.block_count_deserializer.deserialize(input)
+                    }),
+                ),
+            )),
+        )
+        .parse(buffer)
+    }
+}
+
+/// Failure reporting at `input` that a budget of `BootstrapLimits` is exceeded
+fn budget_exceeded<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+    input: &'a [u8],
+    message: &'static str,
+) -> nom::Err<E> {
+    nom::Err::Failure(ContextError::add_context(
+        input,
+        message,
+        ParseError::from_error_kind(input, ErrorKind::TooLarge),
+    ))
+}
+
+/// Parses a value of at most `max_len` bytes at `position` in `buffer`, reading more bytes
+/// from `reader` into it as long as the parser fails for lack of data, then moves `position` after it.
+///
+/// The parsed bytes are only dropped from `buffer` when more bytes are needed, and each read
+/// at least doubles the pending bytes, so that a value is parsed a logarithmic number of times.
+fn read_parsed<R: BufRead, T>(
+    reader: &mut R,
+    buffer: &mut Vec<u8>,
+    position: &mut usize,
+    max_len: usize,
+    parser: impl Fn(&[u8]) -> IResult<&[u8], T, DeserializeError>,
+) -> Result<T, GraphError> {
+    loop {
+        let error = match parser(&buffer[*position..]) {
+            Ok((rest, value)) => {
+                *position = buffer.len() - rest.len();
+                return Ok(value);
+            }
+            Err(nom::Err::Failure(error)) => {
+                return Err(GraphError::InvalidStream(error.to_string()));
+            }
+            Err(error) => error.to_string(),
+        };
+        let pending = buffer.len() - *position;
+        if pending >= max_len {
+            return Err(GraphError::InvalidStream(format!(
+                "no value could be parsed from {} bytes: {}",
+                max_len, error
+            )));
+        }
+        buffer.drain(..*position);
+        *position = 0;
+        let target = pending.saturating_mul(2).clamp(pending + 1, max_len);
+        while buffer.len() < target {
+            let chunk = reader.fill_buf()?;
+            if chunk.is_empty() {
+                break;
+            }
+            let chunk_len = chunk.len().min(max_len - buffer.len());
+            buffer.extend_from_slice(&chunk[..chunk_len]);
+            reader.consume(chunk_len);
+        }
+        if buffer.len() == pending {
+            return Err(GraphError::InvalidStream(error));
         }
     }
 }
@@ -119,19 +1827,126 @@ impl Deserializer<BootstrapableGraph> for BootstrapableGraphDeserializer {
     ) -> IResult<&'a [u8], BootstrapableGraph, E> {
         context(
             "Failed BootstrapableGraph deserialization",
-            tuple((context(
-                "Failed active_blocks deserialization",
-                length_count(
-                    context("Failed final block count deserialization", |input| {
-                        self.block_count_deserializer.deserialize(input)
-                    }),
-                    context("Failed export_active_block deserialization", |input| {
-                        self.export_active_block_deserializer.deserialize(input)
-                    }),
-                ),
-            ),)),
+            |input: &'a [u8]| {
+                // merkle root
+                let (rest, expected_merkle_root) = if self.with_merkle_root {
+                    context("Failed merkle root deserialization", |input| {
+                        self.hash_deserializer.deserialize(input)
+                    })
+                    .map(Some)
+                    .parse(input)?
+                } else {
+                    (input, None)
+                };
+
+                // checksum header byte and checksum
+                let (rest, expected_checksum) = if self.with_checksum {
+                    let (rest, checksum) = context(
+                        "Failed checksum hash deserialization",
+                        |input: &'a [u8]| match input.split_first() {
+                            Some((byte, rest)) => match ChecksumHash::from_byte(*byte) {
+                                Some(checksum) => Ok((rest, checksum)),
+                                None => Err(nom::Err::Failure(ContextError::add_context(
+                                    input,
+                                    "Unknown checksum hash function",
+                                    ParseError::from_error_kind(input, ErrorKind::Verify),
+                                ))),
+                            },
+                            None => Err(nom::Err::Error(ParseError::from_error_kind(
+                                input,
+                                ErrorKind::Eof,
+                            ))),
+                        },
+                    )
+                    .parse(rest)?;
+                    let (rest, expected) = context(
+                        "Failed checksum deserialization",
+                        take(checksum.digest_len()),
+                    )
+                    .parse(rest)?;
+                    (rest, Some((checksum, expected)))
+                } else {
+                    (rest, None)
+                };
+
+                // final blocks, along with their serialized form
+                let (rest, (serialized_blocks, final_blocks)): (
+                    &[u8],
+                    (&[u8], Vec<(&[u8], ExportActiveBlock)>),
+                ) = consumed(context(
+                    "Failed active_blocks deserialization",
+                    |input: &'a [u8]| {
+                        let (rest, block_count) =
+                            context("Failed final block count deserialization", |input| {
+                                self.block_count_deserializer.deserialize(input)
+                            })
+                            .parse(input)?;
+                        if (block_count as usize).saturating_mul(MIN_EXPORT_ACTIVE_BLOCK_SIZE)
+                            > rest.len()
+                        {
+                            return Err(nom::Err::Failure(ContextError::add_context(
+                                input,
+                                "Final block count too large for the remaining data",
+                                ParseError::from_error_kind(input, ErrorKind::TooLarge),
+                            )));
+                        }
+                        let mut final_blocks = Vec::with_capacity(block_count as usize);
+                        let mut rest = rest;
+                        let mut budget = BlockBudget::default();
+                        for _ in 0..block_count {
+                            let (block_rest, (serialized_block, block)) = consumed(context(
+                                "Failed export_active_block deserialization",
+                                |input| self.export_active_block_deserializer.deserialize(input),
+                            ))
+                            .parse(rest)?;
+                            self.check_budget(&mut budget, &block, serialized_block.len())
+                                .map_err(|message| budget_exceeded(rest, message))?;
+                            final_blocks.push((serialized_block, block));
+                            rest = block_rest;
+                        }
+                        Ok((rest, final_blocks))
+                    },
+                ))
+                .parse(rest)?;
+
+                if let Some((checksum, expected)) = expected_checksum {
+                    if !checksum.verify_hash(serialized_blocks, expected) {
+                        return Err(nom::Err::Failure(ContextError::add_context(
+                            rest,
+                            "Checksum does not match the final blocks",
+                            ParseError::from_error_kind(rest, ErrorKind::Verify),
+                        )));
+                    }
+                }
+
+                if let Some(expected_merkle_root) = expected_merkle_root {
+                    let merkle_root = compute_root(
+                        final_blocks
+                            .iter()
+                            .map(|(serialized_block, _)| leaf_hash(serialized_block))
+                            .collect(),
+                    );
+                    if merkle_root != expected_merkle_root {
+                        return Err(nom::Err::Failure(ContextError::add_context(
+                            rest,
+                            "Merkle root does not match the final blocks",
+                            ParseError::from_error_kind(rest, ErrorKind::Verify),
+                        )));
+                    }
+                }
+
+                Ok((
+                    rest,
+                    BootstrapableGraph {
+                        final_blocks: final_blocks.into_iter().map(|(_, block)| block).collect(),
+                    },
+                ))
+            },
         )
-        .map(|(final_blocks,)| BootstrapableGraph { final_blocks })
         .parse(buffer)
     }
 }
+
+/// Deserializer for an optional `BootstrapableGraph`, see `OptionalBootstrapableGraphSerializer`
+pub type OptionalBootstrapableGraphDeserializer =
+    OptionDeserializer<BootstrapableGraph, BootstrapableGraphDeserializer>;
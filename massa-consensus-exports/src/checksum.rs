@@ -0,0 +1,156 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! Integrity checksum of a serialized `BootstrapableGraph`.
+//!
+//! The checksum covers the block count and the final blocks. It is preceded by a header byte
+//! recording the hash function it was computed with, so that deployments verifying snapshots
+//! with SHA-256 tooling can use it instead of the default `massa_hash::Hash`.
+//!
+//! `BootstrapableGraphVerifier` checks the checksum of a serialized graph fed in chunks,
+//! without holding the whole buffer.
+
+use crate::error::GraphError;
+use massa_hash::{Hash, SmtHasher, HASH_SIZE_BYTES};
+use sha2::{Digest, Sha256};
+
+/// Hash function of the integrity checksum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumHash {
+    /// `massa_hash::Hash`
+    Massa,
+    /// SHA-256
+    Sha256,
+}
+
+impl ChecksumHash {
+    /// Header byte recording the hash function
+    pub fn to_byte(self) -> u8 {
+        match self {
+            ChecksumHash::Massa => 0,
+            ChecksumHash::Sha256 => 1,
+        }
+    }
+
+    /// Hash function recorded by a header byte, `None` if the byte is unknown
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(ChecksumHash::Massa),
+            1 => Some(ChecksumHash::Sha256),
+            _ => None,
+        }
+    }
+
+    /// Size of the checksum in bytes
+    pub fn digest_len(self) -> usize {
+        match self {
+            ChecksumHash::Massa => HASH_SIZE_BYTES,
+            ChecksumHash::Sha256 => 32,
+        }
+    }
+
+    /// Computes the checksum of `data`
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            ChecksumHash::Massa => Hash::compute_from(data).to_bytes().to_vec(),
+            ChecksumHash::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    /// Checks `expected` against the checksum of `data`
+    pub fn verify_hash(self, data: &[u8], expected: &[u8]) -> bool {
+        self.digest(data) == expected
+    }
+
+    /// Creates an incremental hasher computing the same checksum as `digest`
+    fn hasher(self) -> ChecksumHasher {
+        match self {
+            // `SmtHasher` wraps the incremental form of the hash behind `massa_hash::Hash`
+            ChecksumHash::Massa => ChecksumHasher::Massa(SmtHasher::new()),
+            ChecksumHash::Sha256 => ChecksumHasher::Sha256(Sha256::new()),
+        }
+    }
+}
+
+/// Incremental computation of a checksum
+enum ChecksumHasher {
+    Massa(SmtHasher),
+    Sha256(Sha256),
+}
+
+impl ChecksumHasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            ChecksumHasher::Massa(hasher) => Digest::update(hasher, data),
+            ChecksumHasher::Sha256(hasher) => Digest::update(hasher, data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            ChecksumHasher::Massa(hasher) => hasher.finalize().to_vec(),
+            ChecksumHasher::Sha256(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+/// Streaming verifier of the integrity checksum of a graph serialized by
+/// `BootstrapableGraphSerializer::new_with_checksum`.
+///
+/// The serialized graph is fed in chunks of any size with `update`, and `finish` compares
+/// the checksum of the final blocks against the one of the header. Only the header is buffered.
+/// Graphs also serialized with a Merkle root are not supported: the root is checked by
+/// `BootstrapableGraphDeserializer::new_with_merkle_root`, which needs the block boundaries.
+#[derive(Default)]
+pub struct BootstrapableGraphVerifier {
+    /// checksum header byte and expected checksum, until complete
+    header: Vec<u8>,
+    /// hash function and expected checksum, once the header is complete
+    expected: Option<(ChecksumHasher, Vec<u8>)>,
+}
+
+impl BootstrapableGraphVerifier {
+    /// Creates a `BootstrapableGraphVerifier`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds the next chunk of the serialized graph
+    pub fn update(&mut self, mut chunk: &[u8]) -> Result<(), GraphError> {
+        // buffer the checksum header byte and the expected checksum
+        while self.expected.is_none() {
+            let (byte, rest) = match chunk.split_first() {
+                Some(split) => split,
+                None => return Ok(()),
+            };
+            self.header.push(*byte);
+            chunk = rest;
+            let checksum = ChecksumHash::from_byte(self.header[0]).ok_or_else(|| {
+                GraphError::InvalidChecksum(format!(
+                    "unknown checksum hash function {}",
+                    self.header[0]
+                ))
+            })?;
+            if self.header.len() == 1 + checksum.digest_len() {
+                self.expected = Some((checksum.hasher(), self.header.split_off(1)));
+            }
+        }
+
+        if let Some((hasher, _)) = &mut self.expected {
+            hasher.update(chunk);
+        }
+        Ok(())
+    }
+
+    /// Checks the checksum once the whole serialized graph has been fed
+    pub fn finish(self) -> Result<(), GraphError> {
+        let (hasher, expected) = self.expected.ok_or_else(|| {
+            GraphError::InvalidChecksum("checksum header missing or incomplete".to_string())
+        })?;
+        if hasher.finalize() != expected {
+            return Err(GraphError::InvalidChecksum(
+                "checksum does not match the final blocks".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
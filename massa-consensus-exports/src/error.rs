@@ -1,8 +1,11 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 use displaydoc::Display;
 use massa_execution_exports::ExecutionError;
+use massa_models::block_id::BlockId;
 use massa_models::error::ModelsError;
+use massa_models::slot::Slot;
 use massa_protocol_exports::ProtocolError;
+use massa_serialization::SerializeError;
 use massa_time::TimeError;
 use std::array::TryFromSliceError;
 use thiserror::Error;
@@ -68,3 +71,43 @@ pub enum LedgerError {
     /// serde error
     SerdeError(#[from] serde_json::Error),
 }
+
+/// Bootstrapable graph error
+#[non_exhaustive]
+#[derive(Display, Error, Debug)]
+pub enum GraphError {
+    /// cycle detected in the parents of block {0}
+    ParentCycle(BlockId),
+    /// blocks {1} and {2} both occupy slot {0}
+    DuplicateSlot(Slot, BlockId, BlockId),
+    /// fitness overflow in thread {0}
+    FitnessOverflow(u8),
+    /// block {0} is in thread {1} but there are only {2} threads
+    ThreadOutOfRange(BlockId, u8, u8),
+    /// block {0} is inconsistent with its thread: {1}
+    ThreadInconsistency(BlockId, String),
+    /// invalid graph delta: {0}
+    InvalidDelta(String),
+    /// block {0} is not in the graph
+    UnknownBlock(BlockId),
+    /// total fitness of the final blocks above the maximum {0}
+    ExcessiveFitness(u64),
+    /// invalid checksum: {0}
+    InvalidChecksum(String),
+    /// block {0} is not final
+    NotFinal(BlockId),
+    /// missing genesis blocks for threads {0:?}
+    MissingGenesis(Vec<u8>),
+    /// I/O error: {0}
+    IoError(#[from] std::io::Error),
+    /// invalid graph stream: {0}
+    InvalidStream(String),
+    /// invalid protobuf message: {0}
+    InvalidProtobuf(String),
+    /// target fitness {0} above the total fitness {1} of the final blocks
+    InsufficientFitness(u64, u64),
+    /// parent {1} of block {0} is not in the graph
+    MissingParent(BlockId, BlockId),
+    /// serialization error: {0}
+    SerializeError(#[from] SerializeError),
+}
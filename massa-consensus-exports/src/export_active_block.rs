@@ -1,9 +1,12 @@
-use crate::error::ConsensusError;
+use crate::error::{ConsensusError, GraphError};
 use massa_hash::HashDeserializer;
 use massa_models::{
     active_block::ActiveBlock,
-    block::{Block, BlockDeserializer, BlockDeserializerArgs, SecureShareBlock},
+    block::{Block, BlockDeserializer, BlockDeserializerArgs, BlockSerializer, SecureShareBlock},
+    block_header::{BlockHeader, BlockHeaderDeserializer, SecuredHeader},
     block_id::BlockId,
+    endorsement::EndorsementId,
+    operation::{OperationId, OperationIdsDeserializer, OperationIdsSerializer},
     prehash::PreHashMap,
     secure_share::{SecureShareDeserializer, SecureShareSerializer},
 };
@@ -15,13 +18,14 @@ use nom::branch::alt;
 use nom::{
     bytes::complete::tag,
     combinator::value,
-    error::{ContextError, ParseError},
+    error::{ContextError, ErrorKind, ParseError},
     multi::count,
     sequence::{preceded, tuple},
 };
 use nom::{error::context, IResult, Parser};
 use serde::{Deserialize, Serialize};
 use std::ops::Bound::Included;
+use std::ops::Range;
 
 /// Exportable version of `ActiveBlock`
 /// Fields that can be easily recomputed were left out
@@ -85,6 +89,118 @@ impl ExportActiveBlock {
 
         Ok((active_block, storage))
     }
+
+    /// Returns the ids of the endorsements included in the block header
+    pub fn endorsement_ids(&self) -> impl Iterator<Item = EndorsementId> + '_ {
+        self.block
+            .content
+            .header
+            .content
+            .endorsements
+            .iter()
+            .map(|endorsement| endorsement.id)
+    }
+
+    /// Checks that the thread of the block slot is below `thread_count`,
+    /// that there is one parent per thread (or none), that the parent of each thread
+    /// is the header parent of that thread,
+    /// and that the endorsements of the block are in the same thread as the block.
+    pub fn validate_thread_consistency(&self, thread_count: u8) -> Result<(), GraphError> {
+        let id = self.block.id;
+        let header = &self.block.content.header.content;
+        let thread = header.slot.thread;
+        if thread >= thread_count {
+            return Err(GraphError::ThreadOutOfRange(id, thread, thread_count));
+        }
+        if !header.parents.is_empty() && header.parents.len() != thread_count as usize {
+            return Err(GraphError::ThreadInconsistency(
+                id,
+                format!(
+                    "{} header parents for {} threads",
+                    header.parents.len(),
+                    thread_count
+                ),
+            ));
+        }
+        if !self.parents.is_empty() && self.parents.len() != thread_count as usize {
+            return Err(GraphError::ThreadInconsistency(
+                id,
+                format!(
+                    "{} parents for {} threads",
+                    self.parents.len(),
+                    thread_count
+                ),
+            ));
+        }
+        if let Some((parent_thread, (parent_id, _))) =
+            self.parents
+                .iter()
+                .enumerate()
+                .find(|(parent_thread, (parent_id, _))| {
+                    header.parents.get(*parent_thread) != Some(parent_id)
+                })
+        {
+            return Err(GraphError::ThreadInconsistency(
+                id,
+                format!(
+                    "parent {} of thread {} is not the header parent of that thread",
+                    parent_id, parent_thread
+                ),
+            ));
+        }
+        if let Some(endorsement) = header
+            .endorsements
+            .iter()
+            .find(|endorsement| endorsement.content.slot.thread != thread)
+        {
+            return Err(GraphError::ThreadInconsistency(
+                id,
+                format!(
+                    "endorsement {} is in thread {}",
+                    endorsement.id, endorsement.content.slot.thread
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// `ExportActiveBlock` without the operation ids of its block
+#[derive(Debug, Clone)]
+pub struct ExportActiveBlockHeader {
+    /// The header of the block.
+    pub header: SecuredHeader,
+    /// one `(block id, period)` per thread ( if not genesis )
+    pub parents: Vec<(BlockId, u64)>,
+    /// for example has its fitness reached the given threshold
+    pub is_final: bool,
+}
+
+impl ExportActiveBlockHeader {
+    /// Rebuilds the full `ExportActiveBlock` from its header and the operation ids of its block
+    pub fn into_export_active_block(
+        self,
+        operations: Vec<OperationId>,
+    ) -> Result<ExportActiveBlock, SerializeError> {
+        let content = Block {
+            header: self.header,
+            operations,
+        };
+        let mut serialized_data = Vec::new();
+        BlockSerializer::new().serialize(&content, &mut serialized_data)?;
+        Ok(ExportActiveBlock {
+            block: SecureShareBlock {
+                signature: content.header.signature,
+                content_creator_pub_key: content.header.content_creator_pub_key,
+                content_creator_address: content.header.content_creator_address,
+                id: content.header.id,
+                content,
+                serialized_data,
+            },
+            parents: self.parents,
+            is_final: self.is_final,
+        })
+    }
 }
 
 /// Basic serializer of `ExportActiveBlock`
@@ -92,6 +208,7 @@ impl ExportActiveBlock {
 pub struct ExportActiveBlockSerializer {
     sec_share_serializer: SecureShareSerializer,
     period_serializer: U64VarIntSerializer,
+    op_ids_serializer: OperationIdsSerializer,
 }
 
 impl ExportActiveBlockSerializer {
@@ -100,19 +217,66 @@ impl ExportActiveBlockSerializer {
         ExportActiveBlockSerializer {
             sec_share_serializer: SecureShareSerializer::new(),
             period_serializer: U64VarIntSerializer::new(),
+            op_ids_serializer: OperationIdsSerializer::new(),
         }
     }
-}
 
-impl Serializer<ExportActiveBlock> for ExportActiveBlockSerializer {
-    fn serialize(
+    /// Serializes the block header, parents and finality of an `ExportActiveBlock`,
+    /// leaving out the operation ids of its block
+    pub fn serialize_header(
         &self,
         value: &ExportActiveBlock,
         buffer: &mut Vec<u8>,
     ) -> Result<(), SerializeError> {
-        // block
+        self.sec_share_serializer
+            .serialize(&value.block.content.header, buffer)?;
+        self.serialize_parents_and_finality(value, buffer)
+    }
+
+    /// Serializes the operation ids of the block of an `ExportActiveBlock`
+    pub fn serialize_body(
+        &self,
+        value: &ExportActiveBlock,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
+        self.op_ids_serializer
+            .serialize(&value.block.content.operations, buffer)
+    }
+
+    /// Serializes an `ExportActiveBlock` like `serialize`, adding to `annotations` the byte range
+    /// of each of its fields in `buffer`, labeled `{label}.{field}`
+    pub(crate) fn serialize_annotated(
+        &self,
+        value: &ExportActiveBlock,
+        label: &str,
+        buffer: &mut Vec<u8>,
+        annotations: &mut Vec<(String, Range<usize>)>,
+    ) -> Result<(), SerializeError> {
+        // the block is written as its content alone: its header, then its operation ids
+        let start = buffer.len();
         self.sec_share_serializer.serialize(&value.block, buffer)?;
+        let block_end = buffer.len();
+        let mut header = Vec::new();
+        self.sec_share_serializer
+            .serialize(&value.block.content.header, &mut header)?;
+        let header_end = start + header.len();
+
+        self.serialize_parents_and_finality(value, buffer)?;
+        let finality_start = buffer.len() - 1;
+        annotations.extend([
+            (format!("{}.header", label), start..header_end),
+            (format!("{}.operations", label), header_end..block_end),
+            (format!("{}.parents", label), block_end..finality_start),
+            (format!("{}.is_final", label), finality_start..buffer.len()),
+        ]);
+        Ok(())
+    }
 
+    fn serialize_parents_and_finality(
+        &self,
+        value: &ExportActiveBlock,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
         // parents with periods
         // note: there should be no parents for genesis blocks
         buffer.push(u8::from(!value.parents.is_empty()));
@@ -128,12 +292,29 @@ impl Serializer<ExportActiveBlock> for ExportActiveBlockSerializer {
     }
 }
 
+impl Serializer<ExportActiveBlock> for ExportActiveBlockSerializer {
+    fn serialize(
+        &self,
+        value: &ExportActiveBlock,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
+        // block
+        self.sec_share_serializer.serialize(&value.block, buffer)?;
+
+        // parents and finality
+        self.serialize_parents_and_finality(value, buffer)
+    }
+}
+
 /// Basic deserializer of `ExportActiveBlock`
 pub struct ExportActiveBlockDeserializer {
     sec_share_block_deserializer: SecureShareDeserializer<Block, BlockDeserializer>,
+    sec_share_header_deserializer: SecureShareDeserializer<BlockHeader, BlockHeaderDeserializer>,
+    op_ids_deserializer: OperationIdsDeserializer,
     hash_deserializer: HashDeserializer,
     period_deserializer: U64VarIntDeserializer,
     thread_count: u8,
+    validate_threads: bool,
 }
 
 impl ExportActiveBlockDeserializer {
@@ -142,15 +323,110 @@ impl ExportActiveBlockDeserializer {
     #[allow(clippy::too_many_arguments)]
     pub fn new(block_der_args: BlockDeserializerArgs) -> Self {
         let thread_count = block_der_args.thread_count;
+        let sec_share_header_deserializer =
+            SecureShareDeserializer::new(BlockHeaderDeserializer::new(
+                block_der_args.thread_count,
+                block_der_args.endorsement_count,
+                block_der_args.max_denunciations_per_block_header,
+                block_der_args.last_start_period,
+            ));
+        let op_ids_deserializer =
+            OperationIdsDeserializer::new(block_der_args.max_operations_per_block);
         ExportActiveBlockDeserializer {
             sec_share_block_deserializer: SecureShareDeserializer::new(BlockDeserializer::new(
                 block_der_args,
             )),
+            sec_share_header_deserializer,
+            op_ids_deserializer,
             hash_deserializer: HashDeserializer::new(),
             period_deserializer: U64VarIntDeserializer::new(Included(0), Included(u64::MAX)),
             thread_count,
+            validate_threads: false,
+        }
+    }
+
+    /// Creates an `ExportActiveBlockDeserializer` that also rejects blocks failing
+    /// `ExportActiveBlock::validate_thread_consistency`
+    pub fn new_with_thread_validation(block_der_args: BlockDeserializerArgs) -> Self {
+        Self {
+            validate_threads: true,
+            ..Self::new(block_der_args)
         }
     }
+
+    /// Deserializes the part of an `ExportActiveBlock` written by `ExportActiveBlockSerializer::serialize_header`
+    pub fn deserialize_header<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], ExportActiveBlockHeader, E> {
+        context(
+            "Failed ExportActiveBlockHeader deserialization",
+            tuple((
+                context("Failed header deserialization", |input| {
+                    self.sec_share_header_deserializer.deserialize(input)
+                }),
+                |input| self.deserialize_parents(input),
+                |input| self.deserialize_finality(input),
+            )),
+        )
+        .map(|(header, parents, is_final)| ExportActiveBlockHeader {
+            header,
+            parents,
+            is_final,
+        })
+        .parse(buffer)
+    }
+
+    /// Deserializes the part of an `ExportActiveBlock` written by `ExportActiveBlockSerializer::serialize_body`
+    pub fn deserialize_body<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], Vec<OperationId>, E> {
+        context("Failed operations deserialization", |input| {
+            self.op_ids_deserializer.deserialize(input)
+        })
+        .parse(buffer)
+    }
+
+    fn deserialize_parents<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], Vec<(BlockId, u64)>, E> {
+        context(
+            "Failed parents deserialization",
+            alt((
+                value(Vec::new(), tag(&[0])),
+                preceded(
+                    tag(&[1]),
+                    count(
+                        tuple((
+                            context("Failed block_id deserialization", |input| {
+                                self.hash_deserializer
+                                    .deserialize(input)
+                                    .map(|(rest, hash)| (rest, BlockId(hash)))
+                            }),
+                            context("Failed period deserialization", |input| {
+                                self.period_deserializer.deserialize(input)
+                            }),
+                        )),
+                        self.thread_count as usize,
+                    ),
+                ),
+            )),
+        )
+        .parse(buffer)
+    }
+
+    fn deserialize_finality<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], bool, E> {
+        context(
+            "Failed is_final deserialization",
+            alt((value(true, tag(&[1])), value(false, tag(&[0])))),
+        )
+        .parse(buffer)
+    }
 }
 
 impl Deserializer<ExportActiveBlock> for ExportActiveBlockDeserializer {
@@ -241,33 +517,9 @@ impl Deserializer<ExportActiveBlock> for ExportActiveBlockDeserializer {
                     self.sec_share_block_deserializer.deserialize(input)
                 }),
                 // parents
-                context(
-                    "Failed parents deserialization",
-                    alt((
-                        value(Vec::new(), tag(&[0])),
-                        preceded(
-                            tag(&[1]),
-                            count(
-                                tuple((
-                                    context("Failed block_id deserialization", |input| {
-                                        self.hash_deserializer
-                                            .deserialize(input)
-                                            .map(|(rest, hash)| (rest, BlockId(hash)))
-                                    }),
-                                    context("Failed period deserialization", |input| {
-                                        self.period_deserializer.deserialize(input)
-                                    }),
-                                )),
-                                self.thread_count as usize,
-                            ),
-                        ),
-                    )),
-                ),
+                |input| self.deserialize_parents(input),
                 // finality
-                context(
-                    "Failed is_final deserialization",
-                    alt((value(true, tag(&[1])), value(false, tag(&[0])))),
-                ),
+                |input| self.deserialize_finality(input),
             )),
         )
         .map(|(block, parents, is_final)| ExportActiveBlock {
@@ -276,5 +528,19 @@ impl Deserializer<ExportActiveBlock> for ExportActiveBlockDeserializer {
             is_final,
         })
         .parse(buffer)
+        .and_then(|(rest, export_active_block)| {
+            if self.validate_threads
+                && export_active_block
+                    .validate_thread_consistency(self.thread_count)
+                    .is_err()
+            {
+                return Err(nom::Err::Failure(ContextError::add_context(
+                    buffer,
+                    "Block is inconsistent with its thread",
+                    ParseError::from_error_kind(buffer, ErrorKind::Verify),
+                )));
+            }
+            Ok((rest, export_active_block))
+        })
     }
 }
@@ -0,0 +1,237 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! Compact chain of the final block headers of a `BootstrapableGraph`.
+//!
+//! Each `BlockHeaderLight` only keeps what a header-only light client needs to check
+//! the topology and the finality of the graph: the block id, its slot, its parents and its fitness.
+//! Block bodies, endorsements and operations are left out.
+
+use crate::export_active_block::ExportActiveBlock;
+use massa_hash::HashDeserializer;
+use massa_models::{
+    block_id::BlockId,
+    slot::{Slot, SlotDeserializer, SlotSerializer},
+};
+use massa_serialization::{
+    Deserializer, SerializeError, Serializer, U32VarIntDeserializer, U32VarIntSerializer,
+    U64VarIntDeserializer, U64VarIntSerializer,
+};
+use nom::branch::alt;
+use nom::{
+    bytes::complete::tag,
+    combinator::value,
+    error::{context, ContextError, ParseError},
+    multi::{count, length_count},
+    sequence::{preceded, tuple},
+    IResult, Parser,
+};
+use serde::{Deserialize, Serialize};
+use std::ops::Bound::{Excluded, Included};
+
+/// Minimal header of a final block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeaderLight {
+    /// block id
+    pub id: BlockId,
+    /// block slot
+    pub slot: Slot,
+    /// one `(block id, period)` per thread ( if not genesis )
+    pub parents: Vec<(BlockId, u64)>,
+    /// block fitness
+    pub fitness: u64,
+}
+
+impl From<&ExportActiveBlock> for BlockHeaderLight {
+    fn from(export_active_block: &ExportActiveBlock) -> Self {
+        BlockHeaderLight {
+            id: export_active_block.block.id,
+            slot: export_active_block.block.content.header.content.slot,
+            parents: export_active_block.parents.clone(),
+            fitness: export_active_block.block.get_fitness(),
+        }
+    }
+}
+
+/// Basic serializer of `BlockHeaderLight`
+#[derive(Default)]
+pub struct BlockHeaderLightSerializer {
+    slot_serializer: SlotSerializer,
+    u64_serializer: U64VarIntSerializer,
+}
+
+impl BlockHeaderLightSerializer {
+    /// Creates a `BlockHeaderLightSerializer`
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Serializer<BlockHeaderLight> for BlockHeaderLightSerializer {
+    fn serialize(
+        &self,
+        value: &BlockHeaderLight,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
+        buffer.extend(value.id.0.to_bytes());
+        self.slot_serializer.serialize(&value.slot, buffer)?;
+
+        // parents with periods, same layout as in `ExportActiveBlock`
+        buffer.push(u8::from(!value.parents.is_empty()));
+        for (hash, period) in value.parents.iter() {
+            buffer.extend(hash.0.to_bytes());
+            self.u64_serializer.serialize(period, buffer)?;
+        }
+
+        self.u64_serializer.serialize(&value.fitness, buffer)
+    }
+}
+
+/// Basic deserializer of `BlockHeaderLight`
+pub struct BlockHeaderLightDeserializer {
+    hash_deserializer: HashDeserializer,
+    slot_deserializer: SlotDeserializer,
+    u64_deserializer: U64VarIntDeserializer,
+    thread_count: u8,
+}
+
+impl BlockHeaderLightDeserializer {
+    /// Creates a `BlockHeaderLightDeserializer`
+    pub fn new(thread_count: u8) -> Self {
+        Self {
+            hash_deserializer: HashDeserializer::new(),
+            slot_deserializer: SlotDeserializer::new(
+                (Included(0), Included(u64::MAX)),
+                (Included(0), Excluded(thread_count)),
+            ),
+            u64_deserializer: U64VarIntDeserializer::new(Included(0), Included(u64::MAX)),
+            thread_count,
+        }
+    }
+
+    fn deserialize_block_id<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], BlockId, E> {
+        context("Failed block_id deserialization", |input| {
+            self.hash_deserializer
+                .deserialize(input)
+                .map(|(rest, hash)| (rest, BlockId(hash)))
+        })
+        .parse(buffer)
+    }
+}
+
+impl Deserializer<BlockHeaderLight> for BlockHeaderLightDeserializer {
+    fn deserialize<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], BlockHeaderLight, E> {
+        context(
+            "Failed BlockHeaderLight deserialization",
+            tuple((
+                |input| self.deserialize_block_id(input),
+                context("Failed slot deserialization", |input| {
+                    self.slot_deserializer.deserialize(input)
+                }),
+                context(
+                    "Failed parents deserialization",
+                    alt((
+                        value(Vec::new(), tag(&[0])),
+                        preceded(
+                            tag(&[1]),
+                            count(
+                                tuple((
+                                    |input| self.deserialize_block_id(input),
+                                    context("Failed period deserialization", |input| {
+                                        self.u64_deserializer.deserialize(input)
+                                    }),
+                                )),
+                                self.thread_count as usize,
+                            ),
+                        ),
+                    )),
+                ),
+                context("Failed fitness deserialization", |input| {
+                    self.u64_deserializer.deserialize(input)
+                }),
+            )),
+        )
+        .map(|(id, slot, parents, fitness)| BlockHeaderLight {
+            id,
+            slot,
+            parents,
+            fitness,
+        })
+        .parse(buffer)
+    }
+}
+
+/// Serializer of a chain of `BlockHeaderLight`, as returned by `BootstrapableGraph::header_chain`
+#[derive(Default)]
+pub struct HeaderChainSerializer {
+    header_count_serializer: U32VarIntSerializer,
+    header_serializer: BlockHeaderLightSerializer,
+}
+
+impl HeaderChainSerializer {
+    /// Creates a `HeaderChainSerializer`
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Serializer<Vec<BlockHeaderLight>> for HeaderChainSerializer {
+    fn serialize(
+        &self,
+        value: &Vec<BlockHeaderLight>,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
+        let header_count: u32 = value
+            .len()
+            .try_into()
+            .map_err(|_| SerializeError::NumberTooBig("Too many headers".to_string()))?;
+        self.header_count_serializer
+            .serialize(&header_count, buffer)?;
+        for header in value {
+            self.header_serializer.serialize(header, buffer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deserializer of a chain of `BlockHeaderLight`
+pub struct HeaderChainDeserializer {
+    header_count_deserializer: U32VarIntDeserializer,
+    header_deserializer: BlockHeaderLightDeserializer,
+}
+
+impl HeaderChainDeserializer {
+    /// Creates a `HeaderChainDeserializer` accepting at most `max_headers` headers
+    pub fn new(thread_count: u8, max_headers: u32) -> Self {
+        Self {
+            header_count_deserializer: U32VarIntDeserializer::new(
+                Included(0),
+                Included(max_headers),
+            ),
+            header_deserializer: BlockHeaderLightDeserializer::new(thread_count),
+        }
+    }
+}
+
+impl Deserializer<Vec<BlockHeaderLight>> for HeaderChainDeserializer {
+    fn deserialize<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], Vec<BlockHeaderLight>, E> {
+        context(
+            "Failed header chain deserialization",
+            length_count(
+                context("Failed header count deserialization", |input| {
+                    self.header_count_deserializer.deserialize(input)
+                }),
+                |input| self.header_deserializer.deserialize(input),
+            ),
+        )
+        .parse(buffer)
+    }
+}
@@ -8,9 +8,14 @@ mod settings;
 pub mod block_graph_export;
 pub mod block_status;
 pub mod bootstrapable_graph;
+pub mod checksum;
 pub mod error;
 pub mod events;
 pub mod export_active_block;
+pub mod header_chain;
+pub mod merkle;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
 
 pub use channels::ConsensusChannels;
 pub use controller_trait::{ConsensusController, ConsensusManager};
@@ -20,3 +25,6 @@ pub use settings::ConsensusConfig;
 #[cfg(feature = "testing")]
 /// Exports related to tests as Mocks and configurations
 pub mod test_exports;
+
+#[cfg(test)]
+mod tests;
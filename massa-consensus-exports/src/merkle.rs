@@ -0,0 +1,148 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! Merkle tree over the final blocks of a `BootstrapableGraph`.
+//!
+//! Each leaf is the hash of a serialized `ExportActiveBlock`, so that a light client
+//! can check a single block against the root without downloading the whole graph.
+//! Leaves and inner nodes are hashed with distinct prefixes, and the last node of a
+//! level with an odd length is carried to the next level unchanged.
+
+use crate::export_active_block::{ExportActiveBlock, ExportActiveBlockSerializer};
+use massa_hash::{Hash, HASH_SIZE_BYTES};
+use massa_models::{block_id::BlockId, prehash::PreHashSet};
+use massa_serialization::Serializer;
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Hash of a leaf, computed from the serialized `ExportActiveBlock`
+pub(crate) fn leaf_hash(serialized_block: &[u8]) -> Hash {
+    let mut data = Vec::with_capacity(serialized_block.len() + 1);
+    data.push(LEAF_PREFIX);
+    data.extend_from_slice(serialized_block);
+    Hash::compute_from(&data)
+}
+
+/// Hash of an inner node
+pub(crate) fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut data = Vec::with_capacity(2 * HASH_SIZE_BYTES + 1);
+    data.push(NODE_PREFIX);
+    data.extend_from_slice(left.to_bytes());
+    data.extend_from_slice(right.to_bytes());
+    Hash::compute_from(&data)
+}
+
+fn next_level(level: &[Hash]) -> Vec<Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            _ => pair[0],
+        })
+        .collect()
+}
+
+/// Computes the root of the tree built over `leaves`.
+/// The root of an empty tree is the hash of an empty input.
+pub(crate) fn compute_root(mut level: Vec<Hash>) -> Hash {
+    if level.is_empty() {
+        return Hash::compute_from(&[]);
+    }
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// Computes the inclusion proof of the leaf at `index`
+pub(crate) fn compute_proof(mut level: Vec<Hash>, mut index: usize) -> MerkleProof {
+    let mut path = Vec::new();
+    while level.len() > 1 {
+        let sibling_index = index ^ 1;
+        if let Some(sibling) = level.get(sibling_index) {
+            path.push((*sibling, sibling_index < index));
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+    MerkleProof { path }
+}
+
+/// Proof that a block is part of the Merkle tree of a `BootstrapableGraph`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    /// sibling hashes from the leaf up to the root,
+    /// each with `true` if the sibling is the left operand of the node
+    pub path: Vec<(Hash, bool)>,
+}
+
+impl MerkleProof {
+    /// Computes the root reached from the given leaf hash by following the path
+    pub fn root_from_leaf(&self, leaf: Hash) -> Hash {
+        self.path
+            .iter()
+            .fold(leaf, |current, (sibling, sibling_is_left)| {
+                if *sibling_is_left {
+                    node_hash(sibling, &current)
+                } else {
+                    node_hash(&current, sibling)
+                }
+            })
+    }
+
+    /// Returns true if `block` is part of the tree of root `root` according to this proof
+    pub fn verify(&self, block: &ExportActiveBlock, root: &Hash) -> bool {
+        let mut serialized_block = Vec::new();
+        if ExportActiveBlockSerializer::new()
+            .serialize(block, &mut serialized_block)
+            .is_err()
+        {
+            return false;
+        }
+        self.root_from_leaf(leaf_hash(&serialized_block)) == *root
+    }
+}
+
+/// Proof that a block is final, for light clients: the block, marked final,
+/// along with its inclusion path in the Merkle tree of a `BootstrapableGraph`,
+/// and the evidence of its finality: descendants of the block in the graph, each with its inclusion path,
+/// whose total fitness is above the finality threshold `delta_f0`.
+///
+/// See `BootstrapableGraph::finality_proof`.
+#[derive(Debug, Clone)]
+pub struct FinalityProof {
+    /// the final block
+    pub block: ExportActiveBlock,
+    /// inclusion path of the block
+    pub merkle_proof: MerkleProof,
+    /// descendants of the block, each listed after one of its parents, with their inclusion paths
+    pub descendants: Vec<(ExportActiveBlock, MerkleProof)>,
+}
+
+/// Returns true if `proof` shows a final block that is part of the tree of root `expected_root`,
+/// with descendants in that tree whose total fitness is above `delta_f0`
+pub fn verify_finality_proof(proof: &FinalityProof, expected_root: &Hash, delta_f0: u64) -> bool {
+    if !proof.block.is_final || !proof.merkle_proof.verify(&proof.block, expected_root) {
+        return false;
+    }
+    let mut known: PreHashSet<BlockId> = PreHashSet::default();
+    known.insert(proof.block.block.id);
+    let mut fitness: u64 = 0;
+    for (descendant, merkle_proof) in &proof.descendants {
+        let descends = descendant
+            .parents
+            .iter()
+            .any(|(parent_id, _)| known.contains(parent_id));
+        if !descends
+            || !known.insert(descendant.block.id)
+            || !merkle_proof.verify(descendant, expected_root)
+        {
+            return false;
+        }
+        match fitness.checked_add(descendant.block.get_fitness()) {
+            Some(total) => fitness = total,
+            None => return false,
+        }
+    }
+    fitness > delta_f0
+}
@@ -0,0 +1,159 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+//! Protobuf representation of a `BootstrapableGraph`, for non-Rust tooling.
+//!
+//! The messages mirror `proto/bootstrapable_graph.proto`. Blocks are kept in their binary
+//! serialization so that their signatures can be checked. The binary format of
+//! `BootstrapableGraphSerializer` remains the canonical wire format.
+
+use crate::bootstrapable_graph::{BootstrapLimits, BootstrapableGraph};
+use crate::error::GraphError;
+use crate::export_active_block::ExportActiveBlock;
+use massa_hash::{Hash, HASH_SIZE_BYTES};
+use massa_models::{
+    block::{Block, BlockDeserializer},
+    block_id::BlockId,
+    secure_share::{SecureShareDeserializer, SecureShareSerializer},
+};
+use massa_serialization::{DeserializeError, Deserializer, SerializeError, Serializer};
+use nom::combinator::all_consuming;
+
+/// Protobuf message of a `BootstrapableGraph`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BootstrapableGraphProto {
+    /// final blocks
+    #[prost(message, repeated, tag = "1")]
+    pub final_blocks: Vec<ExportActiveBlockProto>,
+}
+
+/// Protobuf message of an `ExportActiveBlock`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ExportActiveBlockProto {
+    /// binary serialization of the signed block: its content alone, the signed header followed by the operation ids
+    #[prost(bytes = "vec", tag = "1")]
+    pub block: Vec<u8>,
+    /// one parent per thread, none for genesis blocks
+    #[prost(message, repeated, tag = "2")]
+    pub parents: Vec<BlockParentProto>,
+    /// finality
+    #[prost(bool, tag = "3")]
+    pub is_final: bool,
+}
+
+/// Protobuf message of a block parent
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BlockParentProto {
+    /// block id
+    #[prost(bytes = "vec", tag = "1")]
+    pub block_id: Vec<u8>,
+    /// period
+    #[prost(uint64, tag = "2")]
+    pub period: u64,
+}
+
+impl ExportActiveBlock {
+    /// Converts the block to its protobuf message
+    pub fn to_protobuf(&self) -> Result<ExportActiveBlockProto, SerializeError> {
+        let mut block = Vec::new();
+        SecureShareSerializer::new().serialize(&self.block, &mut block)?;
+        Ok(ExportActiveBlockProto {
+            block,
+            parents: self
+                .parents
+                .iter()
+                .map(|(block_id, period)| BlockParentProto {
+                    block_id: block_id.0.to_bytes().to_vec(),
+                    period: *period,
+                })
+                .collect(),
+            is_final: self.is_final,
+        })
+    }
+
+    /// Converts a protobuf message to a block, deserializing the signed block within the bounds of `limits`
+    pub fn from_protobuf(
+        proto: &ExportActiveBlockProto,
+        limits: BootstrapLimits,
+    ) -> Result<Self, GraphError> {
+        block_from_protobuf(
+            proto,
+            &SecureShareDeserializer::new(BlockDeserializer::new(limits.block_deserializer_args())),
+            limits.thread_count,
+        )
+    }
+}
+
+impl BootstrapableGraph {
+    /// Converts the graph to its protobuf message
+    pub fn to_protobuf(&self) -> Result<BootstrapableGraphProto, SerializeError> {
+        Ok(BootstrapableGraphProto {
+            final_blocks: self
+                .final_blocks
+                .iter()
+                .map(ExportActiveBlock::to_protobuf)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Converts a protobuf message to a graph, within the bounds of `limits`
+    pub fn from_protobuf(
+        proto: &BootstrapableGraphProto,
+        limits: BootstrapLimits,
+    ) -> Result<Self, GraphError> {
+        if proto.final_blocks.len() > limits.max_bootstrap_blocks as usize {
+            return Err(GraphError::InvalidProtobuf(format!(
+                "{} final blocks, above the maximum of {}",
+                proto.final_blocks.len(),
+                limits.max_bootstrap_blocks
+            )));
+        }
+        let block_deserializer =
+            SecureShareDeserializer::new(BlockDeserializer::new(limits.block_deserializer_args()));
+        Ok(BootstrapableGraph {
+            final_blocks: proto
+                .final_blocks
+                .iter()
+                .map(|block| block_from_protobuf(block, &block_deserializer, limits.thread_count))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+fn block_from_protobuf(
+    proto: &ExportActiveBlockProto,
+    block_deserializer: &SecureShareDeserializer<Block, BlockDeserializer>,
+    thread_count: u8,
+) -> Result<ExportActiveBlock, GraphError> {
+    // one parent per thread, or none for genesis blocks, as in the binary form
+    if !proto.parents.is_empty() && proto.parents.len() != thread_count as usize {
+        return Err(GraphError::InvalidProtobuf(format!(
+            "{} parents for {} threads",
+            proto.parents.len(),
+            thread_count
+        )));
+    }
+    let (_, block) =
+        all_consuming(|input| block_deserializer.deserialize::<DeserializeError>(input))(
+            &proto.block,
+        )
+        .map_err(|err| GraphError::InvalidProtobuf(err.to_string()))?;
+    let parents = proto
+        .parents
+        .iter()
+        .map(|parent| {
+            let block_id: [u8; HASH_SIZE_BYTES] =
+                parent.block_id.as_slice().try_into().map_err(|_| {
+                    GraphError::InvalidProtobuf(format!(
+                        "parent block id of {} bytes",
+                        parent.block_id.len()
+                    ))
+                })?;
+            Ok((BlockId(Hash::from_bytes(&block_id)), parent.period))
+        })
+        .collect::<Result<_, GraphError>>()?;
+    Ok(ExportActiveBlock {
+        block,
+        parents,
+        is_final: proto.is_final,
+    })
+}
@@ -0,0 +1,1429 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+use super::tools::{
+    create_export_active_block, create_graph, get_block_deserializer_args, THREAD_COUNT,
+};
+use crate::bootstrapable_graph::{
+    BootstrapLimits, BootstrapableGraph, BootstrapableGraphDeserializer,
+    BootstrapableGraphSerializer, GraphStats, GraphWindow, OptionalBootstrapableGraphDeserializer,
+    OptionalBootstrapableGraphSerializer,
+};
+use crate::checksum::{BootstrapableGraphVerifier, ChecksumHash};
+use crate::error::GraphError;
+use crate::export_active_block::ExportActiveBlockSerializer;
+use crate::merkle::{leaf_hash, node_hash, verify_finality_proof};
+use massa_models::{
+    block_id::BlockId, config::MAX_BLOCK_SIZE, endorsement::EndorsementId, prehash::PreHashSet,
+    secure_share::SecureShareSerializer, slot::Slot,
+};
+use massa_serialization::{DeserializeError, Deserializer, SerializeError, Serializer};
+use massa_signature::KeyPair;
+use std::io::{BufReader, Cursor};
+
+#[test]
+fn test_graph_stats() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 2, 3);
+    let parents = graph.final_blocks[graph.final_blocks.len() - THREAD_COUNT as usize..]
+        .iter()
+        .map(|block| {
+            (
+                block.block.id,
+                block.block.content.header.content.slot.period,
+            )
+        })
+        .collect();
+    graph.final_blocks.push(create_export_active_block(
+        &keypair,
+        Slot::new(3, 1),
+        parents,
+        1,
+        5,
+    ));
+
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    assert_eq!(
+        graph.stats(THREAD_COUNT),
+        GraphStats {
+            block_count: 7,
+            blocks_per_thread: vec![3, 4],
+            operation_count: 5,
+            endorsement_count: 13,
+            highest_period: Some(3),
+            serialized_size: Some(buffer.len()),
+        }
+    );
+}
+
+#[test]
+fn test_merkle_root() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 0, 0);
+    graph.final_blocks.push(create_export_active_block(
+        &keypair,
+        Slot::new(1, 0),
+        graph
+            .final_blocks
+            .iter()
+            .map(|block| (block.block.id, 0))
+            .collect(),
+        0,
+        0,
+    ));
+
+    let serializer = ExportActiveBlockSerializer::new();
+    let leaves: Vec<_> = graph
+        .final_blocks
+        .iter()
+        .map(|block| {
+            let mut buffer = Vec::new();
+            serializer.serialize(block, &mut buffer).unwrap();
+            leaf_hash(&buffer)
+        })
+        .collect();
+    assert_eq!(
+        graph.merkle_root().unwrap(),
+        node_hash(&node_hash(&leaves[0], &leaves[1]), &leaves[2])
+    );
+
+    graph.final_blocks.truncate(1);
+    assert_eq!(graph.merkle_root().unwrap(), leaves[0]);
+}
+
+#[test]
+fn test_merkle_root_serialization() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 1);
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new_with_merkle_root()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    assert_eq!(
+        &buffer[..massa_hash::HASH_SIZE_BYTES],
+        graph.merkle_root().unwrap().to_bytes()
+    );
+
+    let deserializer =
+        BootstrapableGraphDeserializer::new_with_merkle_root(get_block_deserializer_args(), 100);
+    let (rest, deserialized) = deserializer
+        .deserialize::<DeserializeError>(&buffer)
+        .unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(
+        deserialized.merkle_root().unwrap(),
+        graph.merkle_root().unwrap()
+    );
+
+    // tampered root
+    buffer[0] ^= 1;
+    assert!(deserializer
+        .deserialize::<DeserializeError>(&buffer)
+        .is_err());
+}
+
+#[test]
+fn test_merkle_proof() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 0);
+    let root = graph.merkle_root().unwrap();
+    for block in &graph.final_blocks {
+        let proof = graph.merkle_proof(&block.block.id).unwrap().unwrap();
+        assert!(proof.verify(block, &root));
+    }
+
+    // proof of another block
+    let proof = graph
+        .merkle_proof(&graph.final_blocks[0].block.id)
+        .unwrap()
+        .unwrap();
+    assert!(!proof.verify(&graph.final_blocks[1], &root));
+
+    // tampered path
+    let mut tampered = proof.clone();
+    tampered.path[0].1 = !tampered.path[0].1;
+    assert!(!tampered.verify(&graph.final_blocks[0], &root));
+
+    // unknown block
+    let other_graph = create_graph(&keypair, 3, 0);
+    assert!(graph
+        .merkle_proof(&other_graph.final_blocks[7].block.id)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_iter_topological() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 3, 0);
+    graph.final_blocks.reverse();
+
+    let sorted = graph.iter_topological().unwrap();
+    assert_eq!(sorted.len(), graph.final_blocks.len());
+    let mut seen: PreHashSet<BlockId> = PreHashSet::default();
+    for block in sorted {
+        for (parent_id, _) in &block.parents {
+            assert!(seen.contains(parent_id), "parent applied after its child");
+        }
+        seen.insert(block.block.id);
+    }
+}
+
+#[test]
+fn test_iter_topological_cycle() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 2, 0);
+    // block (1, 0) now references its own child (2, 0)
+    let child = (graph.final_blocks[4].block.id, 2);
+    graph.final_blocks[2].parents[1] = child;
+
+    assert!(matches!(
+        graph.iter_topological(),
+        Err(GraphError::ParentCycle(_))
+    ));
+}
+
+#[test]
+fn test_headers_first_serialization() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 2, 2);
+    let parents = graph.final_blocks[graph.final_blocks.len() - THREAD_COUNT as usize..]
+        .iter()
+        .map(|block| (block.block.id, 2))
+        .collect();
+    graph.final_blocks.push(create_export_active_block(
+        &keypair,
+        Slot::new(3, 0),
+        parents,
+        1,
+        3,
+    ));
+
+    let serializer = BootstrapableGraphSerializer::new();
+    let mut buffer = Vec::new();
+    serializer
+        .serialize_headers_first(&graph, &mut buffer)
+        .unwrap();
+
+    // the header chain can be parsed on its own
+    let deserializer = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 100);
+    let (bodies, headers) = deserializer
+        .deserialize_header_section::<DeserializeError>(&buffer)
+        .unwrap();
+    assert!(!bodies.is_empty());
+    assert_eq!(headers.len(), graph.final_blocks.len());
+    for (header, block) in headers.iter().zip(graph.final_blocks.iter()) {
+        assert_eq!(header.header.id, block.block.id);
+        assert_eq!(header.parents, block.parents);
+    }
+
+    // full blocks are rebuilt from both sections
+    let (rest, deserialized) = deserializer
+        .deserialize_headers_first::<DeserializeError>(&buffer)
+        .unwrap();
+    assert!(rest.is_empty());
+    let mut expected = Vec::new();
+    serializer.serialize(&graph, &mut expected).unwrap();
+    let mut reserialized = Vec::new();
+    serializer
+        .serialize(&deserialized, &mut reserialized)
+        .unwrap();
+    assert_eq!(expected, reserialized);
+}
+
+#[test]
+fn test_serialize_with_progress() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 1);
+    let total = graph.final_blocks.len();
+
+    let mut progress = Vec::new();
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize_with_progress(&graph, &mut buffer, |done, total| {
+            progress.push((done, total))
+        })
+        .unwrap();
+    assert_eq!(
+        progress,
+        (1..=total).map(|done| (done, total)).collect::<Vec<_>>()
+    );
+
+    let mut expected_buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut expected_buffer)
+        .unwrap();
+    assert_eq!(buffer, expected_buffer);
+}
+
+#[test]
+fn test_validate_unique_slots() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 2, 1);
+    graph.validate_unique_slots().unwrap();
+
+    // a second block at slot (1, 0), with an operation so that its id differs
+    let duplicate = create_export_active_block(
+        &keypair,
+        Slot::new(1, 0),
+        graph.final_blocks[2].parents.clone(),
+        1,
+        1,
+    );
+    let (original_id, duplicate_id) = (graph.final_blocks[2].block.id, duplicate.block.id);
+    assert_ne!(original_id, duplicate_id);
+    graph.final_blocks.push(duplicate);
+    assert!(matches!(
+        graph.validate_unique_slots(),
+        Err(GraphError::DuplicateSlot(slot, first, second))
+            if slot == Slot::new(1, 0) && first == original_id && second == duplicate_id
+    ));
+}
+
+#[test]
+fn test_leaf_blocks() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 2, 0);
+    // the blocks of the last period are the tips
+    assert_eq!(
+        graph.leaf_blocks(),
+        PreHashSet::from_iter([
+            graph.final_blocks[4].block.id,
+            graph.final_blocks[5].block.id
+        ])
+    );
+
+    // a block at (3, 0) on top of both tips leaves a single tip
+    let parents = graph.final_blocks[4..]
+        .iter()
+        .map(|block| (block.block.id, 2))
+        .collect();
+    let tip = create_export_active_block(&keypair, Slot::new(3, 0), parents, 0, 0);
+    let tip_id = tip.block.id;
+    graph.final_blocks.push(tip);
+    assert_eq!(graph.leaf_blocks(), PreHashSet::from_iter([tip_id]));
+}
+
+#[test]
+fn test_deserialize_with_limits() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let limits = BootstrapLimits {
+        thread_count: THREAD_COUNT,
+        max_bootstrap_blocks: 4,
+        ..BootstrapLimits::default()
+    };
+    assert_eq!(
+        limits.block_deserializer_args().max_operations_per_block,
+        BootstrapLimits::default().max_operations_per_block
+    );
+    let deserializer = BootstrapableGraphDeserializer::with_limits(limits);
+
+    let graph = create_graph(&keypair, 1, 0);
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    let (rest, deserialized) = deserializer
+        .deserialize::<DeserializeError>(&buffer)
+        .unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(deserialized.final_blocks.len(), 4);
+
+    // one more period exceeds max_bootstrap_blocks
+    let graph = create_graph(&keypair, 2, 0);
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    assert!(deserializer
+        .deserialize::<DeserializeError>(&buffer)
+        .is_err());
+}
+
+#[test]
+fn test_deserialize_with_thread_validation() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 1, 0);
+    // the parents of the last block no longer match the threads of its header parents
+    graph.final_blocks.last_mut().unwrap().parents.swap(0, 1);
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    let deserialize = |validate_thread_consistency: bool| {
+        BootstrapableGraphDeserializer::with_limits(BootstrapLimits {
+            thread_count: THREAD_COUNT,
+            validate_thread_consistency,
+            ..BootstrapLimits::default()
+        })
+        .deserialize::<DeserializeError>(&buffer)
+    };
+
+    assert!(deserialize(false).is_ok());
+    let err = deserialize(true).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Block is inconsistent with its thread"));
+
+    // a consistent graph is accepted
+    let graph = create_graph(&keypair, 2, 0);
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    let (rest, deserialized) = BootstrapableGraphDeserializer::with_limits(BootstrapLimits {
+        thread_count: THREAD_COUNT,
+        validate_thread_consistency: true,
+        ..BootstrapLimits::default()
+    })
+    .deserialize::<DeserializeError>(&buffer)
+    .unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(deserialized.final_blocks.len(), graph.final_blocks.len());
+}
+
+#[test]
+fn test_deserialize_with_budgets() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 2, 0);
+    let parents = graph.final_blocks[graph.final_blocks.len() - THREAD_COUNT as usize..]
+        .iter()
+        .map(|block| (block.block.id, 2))
+        .collect();
+    graph.final_blocks.push(create_export_active_block(
+        &keypair,
+        Slot::new(3, 0),
+        parents,
+        0,
+        3,
+    ));
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    let deserialize = |limits: BootstrapLimits| {
+        BootstrapableGraphDeserializer::with_limits(BootstrapLimits {
+            thread_count: THREAD_COUNT,
+            ..limits
+        })
+        .deserialize::<DeserializeError>(&buffer)
+    };
+
+    // the limits reached exactly are accepted, the byte budget excluding the block count
+    let limits = BootstrapLimits {
+        max_bytes: buffer.len() as u64 - 1,
+        max_operations: 3,
+        max_depth: 3,
+        ..BootstrapLimits::default()
+    };
+    let (rest, deserialized) = deserialize(limits).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(deserialized.final_blocks.len(), graph.final_blocks.len());
+
+    // each budget exceeded by one is rejected
+    assert!(deserialize(BootstrapLimits {
+        max_bytes: buffer.len() as u64 - 2,
+        ..limits
+    })
+    .is_err());
+    assert!(deserialize(BootstrapLimits {
+        max_operations: 2,
+        ..limits
+    })
+    .is_err());
+    assert!(deserialize(BootstrapLimits {
+        max_depth: 2,
+        ..limits
+    })
+    .is_err());
+}
+
+#[test]
+fn test_budgets_in_every_deserialization() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let base = create_graph(&keypair, 2, 0);
+    let mut graph = base.clone();
+    let parents = graph.final_blocks[graph.final_blocks.len() - THREAD_COUNT as usize..]
+        .iter()
+        .map(|block| (block.block.id, 2))
+        .collect();
+    graph.final_blocks.push(create_export_active_block(
+        &keypair,
+        Slot::new(3, 0),
+        parents,
+        0,
+        3,
+    ));
+    let serializer = BootstrapableGraphSerializer::new();
+    let mut compressed = Vec::new();
+    serializer
+        .serialize_compressed_blocks(&graph, &mut compressed)
+        .unwrap();
+    let mut headers_first = Vec::new();
+    serializer
+        .serialize_headers_first(&graph, &mut headers_first)
+        .unwrap();
+    let mut window = Vec::new();
+    graph
+        .serialize_window(0..THREAD_COUNT, 0..u64::MAX, &mut window)
+        .unwrap();
+    let mut delta = Vec::new();
+    graph.serialize_delta(&base, &mut delta).unwrap();
+    let mut full = Vec::new();
+    serializer.serialize(&graph, &mut full).unwrap();
+
+    // the added block holds 3 operations
+    for (max_operations, accepted) in [(3, true), (2, false)] {
+        let deserializer = BootstrapableGraphDeserializer::with_limits(BootstrapLimits {
+            thread_count: THREAD_COUNT,
+            max_operations,
+            ..BootstrapLimits::default()
+        });
+        assert_eq!(
+            deserializer
+                .deserialize_compressed_blocks::<DeserializeError>(&compressed)
+                .is_ok(),
+            accepted
+        );
+        assert_eq!(
+            deserializer
+                .deserialize_parallel::<DeserializeError>(&compressed)
+                .is_ok(),
+            accepted
+        );
+        assert_eq!(
+            deserializer
+                .deserialize_headers_first::<DeserializeError>(&headers_first)
+                .is_ok(),
+            accepted
+        );
+        assert_eq!(
+            deserializer
+                .deserialize_window::<DeserializeError>(&window)
+                .is_ok(),
+            accepted
+        );
+        assert_eq!(deserializer.apply_delta(&base, &delta).is_ok(), accepted);
+        assert_eq!(
+            deserializer
+                .deserialize_from_bufread(Cursor::new(&full), |_| {})
+                .is_ok(),
+            accepted
+        );
+    }
+}
+
+#[test]
+fn test_thread_fitness() {
+    let keypair = KeyPair::generate(0).unwrap();
+    // genesis blocks have no endorsements: fitness 1 each
+    // the 2 blocks of each of the 2 later periods have 3 endorsements: fitness 4 each
+    let graph = create_graph(&keypair, 2, 3);
+    assert_eq!(graph.thread_fitness(THREAD_COUNT).unwrap(), vec![9, 9]);
+    assert_eq!(graph.thread_fitness(1).unwrap(), vec![9]);
+}
+
+#[test]
+fn test_optional_graph_sentinel() {
+    let serializer = OptionalBootstrapableGraphSerializer::new(BootstrapableGraphSerializer::new());
+    let deserializer = OptionalBootstrapableGraphDeserializer::new(
+        BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 10),
+    );
+
+    let mut sentinel = Vec::new();
+    serializer.serialize(&None, &mut sentinel).unwrap();
+    assert_eq!(sentinel.len(), 1);
+    let (rest, graph) = deserializer
+        .deserialize::<DeserializeError>(&sentinel)
+        .unwrap();
+    assert!(rest.is_empty());
+    assert!(graph.is_none());
+
+    let mut empty = Vec::new();
+    let empty_graph = BootstrapableGraph {
+        final_blocks: Vec::new(),
+    };
+    serializer
+        .serialize(&Some(empty_graph), &mut empty)
+        .unwrap();
+    assert_ne!(sentinel, empty);
+    let (rest, graph) = deserializer
+        .deserialize::<DeserializeError>(&empty)
+        .unwrap();
+    assert!(rest.is_empty());
+    assert!(graph.unwrap().final_blocks.is_empty());
+}
+
+#[test]
+fn test_checksum_serialization() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 1);
+    let deserializer =
+        BootstrapableGraphDeserializer::new_with_checksum(get_block_deserializer_args(), 10);
+
+    for checksum in [ChecksumHash::Massa, ChecksumHash::Sha256] {
+        let mut buffer = Vec::new();
+        BootstrapableGraphSerializer::new_with_checksum(checksum)
+            .serialize(&graph, &mut buffer)
+            .unwrap();
+        assert_eq!(ChecksumHash::from_byte(buffer[0]), Some(checksum));
+        let (rest, deserialized) = deserializer
+            .deserialize::<DeserializeError>(&buffer)
+            .unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            deserialized
+                .final_blocks
+                .iter()
+                .map(|block| block.block.id)
+                .collect::<Vec<_>>(),
+            graph
+                .final_blocks
+                .iter()
+                .map(|block| block.block.id)
+                .collect::<Vec<_>>()
+        );
+
+        // the last byte is the finality of the last block: flipping it keeps the graph valid
+        let last = buffer.len() - 1;
+        buffer[last] ^= 1;
+        assert!(deserializer
+            .deserialize::<DeserializeError>(&buffer)
+            .is_err());
+    }
+
+    // the hashes do not verify each other's checksums
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new_with_checksum(ChecksumHash::Massa)
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    buffer[0] = ChecksumHash::Sha256.to_byte();
+    assert!(deserializer
+        .deserialize::<DeserializeError>(&buffer)
+        .is_err());
+}
+
+#[test]
+fn test_graph_delta() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let base = create_graph(&keypair, 2, 1);
+    // the new graph drops the genesis blocks and gains the blocks of period 3
+    let mut new = create_graph(&keypair, 3, 1);
+    new.final_blocks.drain(..THREAD_COUNT as usize);
+
+    let mut delta = Vec::new();
+    new.serialize_delta(&base, &mut delta).unwrap();
+    let mut full = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&new, &mut full)
+        .unwrap();
+    assert!(delta.len() < full.len());
+
+    let deserializer = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 10);
+    let applied = deserializer.apply_delta(&base, &delta).unwrap();
+    let mut applied_buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&applied, &mut applied_buffer)
+        .unwrap();
+    assert_eq!(applied_buffer, full);
+
+    // the delta does not apply to a graph missing the removed blocks
+    assert!(matches!(
+        deserializer.apply_delta(&new, &delta),
+        Err(GraphError::InvalidDelta(_))
+    ));
+}
+
+#[test]
+fn test_graph_delta_modified_and_reordered() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let base = create_graph(&keypair, 2, 1);
+    // the new graph keeps the same blocks, with one block no longer final and two blocks swapped
+    let mut new = create_graph(&keypair, 2, 1);
+    new.final_blocks[1].is_final = false;
+    new.final_blocks.swap(2, 4);
+
+    let mut delta = Vec::new();
+    new.serialize_delta(&base, &mut delta).unwrap();
+    let deserializer = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 10);
+    let applied = deserializer.apply_delta(&base, &delta).unwrap();
+
+    let mut full = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&new, &mut full)
+        .unwrap();
+    let mut applied_buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&applied, &mut applied_buffer)
+        .unwrap();
+    assert_eq!(applied_buffer, full);
+    assert!(!applied.final_blocks[1].is_final);
+
+    // an unchanged graph gives an empty delta, with no order
+    let mut delta = Vec::new();
+    base.serialize_delta(&base, &mut delta).unwrap();
+    assert_eq!(delta, vec![0, 0, 0, 0]);
+}
+
+#[test]
+fn test_common_ancestor() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 0);
+    let id = |index: usize| graph.final_blocks[index].block.id;
+
+    // (2, 0) and (2, 1) both descend from every block of periods 0 and 1, (1, 1) being the latest
+    assert_eq!(graph.common_ancestor(&id(4), &id(5)).unwrap(), Some(id(3)));
+    // a block is its own ancestor
+    assert_eq!(graph.common_ancestor(&id(4), &id(2)).unwrap(), Some(id(2)));
+    // genesis blocks have disjoint lineages
+    assert_eq!(graph.common_ancestor(&id(0), &id(1)).unwrap(), None);
+
+    let unknown = BlockId(massa_hash::Hash::compute_from(b"unknown"));
+    assert!(matches!(
+        graph.common_ancestor(&id(0), &unknown),
+        Err(GraphError::UnknownBlock(block_id)) if block_id == unknown
+    ));
+}
+
+#[test]
+fn test_deserialize_arc() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 1, 1);
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+
+    let (rest, blocks) = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 10)
+        .deserialize_arc::<DeserializeError>(&buffer)
+        .unwrap();
+    assert!(rest.is_empty());
+    let shared = std::sync::Arc::clone(&blocks);
+    assert!(std::ptr::eq(blocks.as_ptr(), shared.as_ptr()));
+    assert_eq!(shared.len(), graph.final_blocks.len());
+    for (block, expected) in shared.iter().zip(&graph.final_blocks) {
+        assert_eq!(block.block.id, expected.block.id);
+    }
+}
+
+#[test]
+fn test_validate_max_fitness() {
+    let keypair = KeyPair::generate(0).unwrap();
+    // 2 genesis blocks of fitness 1 and 4 blocks of fitness 4
+    let graph = create_graph(&keypair, 2, 3);
+    graph.validate_max_fitness(18).unwrap();
+    assert!(matches!(
+        graph.validate_max_fitness(17),
+        Err(GraphError::ExcessiveFitness(17))
+    ));
+}
+
+#[test]
+fn test_compressed_blocks() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 3, 2);
+    let deserializer = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 10);
+
+    let mut buffer = Vec::new();
+    let offsets = BootstrapableGraphSerializer::new()
+        .serialize_compressed_blocks(&graph, &mut buffer)
+        .unwrap();
+    assert_eq!(offsets.len(), graph.final_blocks.len());
+
+    // a single block is decompressed from its own bytes only
+    let end = offsets[4];
+    let (rest, block) = deserializer
+        .deserialize_compressed_block::<DeserializeError>(&buffer[offsets[3]..end])
+        .unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(block.block.id, graph.final_blocks[3].block.id);
+
+    let (rest, deserialized) = deserializer
+        .deserialize_compressed_blocks::<DeserializeError>(&buffer)
+        .unwrap();
+    assert!(rest.is_empty());
+    let mut expected = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut expected)
+        .unwrap();
+    let mut serialized = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&deserialized, &mut serialized)
+        .unwrap();
+    assert_eq!(serialized, expected);
+
+    // compressing blocks independently trades ratio for random access
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut encoder, &expected).unwrap();
+    let whole = encoder.finish().unwrap();
+    assert!(whole.len() < buffer.len());
+}
+
+#[test]
+fn test_deserialize_parallel() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 3, 2);
+    let deserializer = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 10);
+    let mut buffer = Vec::new();
+    let offsets = BootstrapableGraphSerializer::new()
+        .serialize_compressed_blocks(&graph, &mut buffer)
+        .unwrap();
+
+    let (rest, sequential) = deserializer
+        .deserialize_compressed_blocks::<DeserializeError>(&buffer)
+        .unwrap();
+    assert!(rest.is_empty());
+    let (rest, parallel) = deserializer
+        .deserialize_parallel::<DeserializeError>(&buffer)
+        .unwrap();
+    assert!(rest.is_empty());
+    let serializer = BootstrapableGraphSerializer::new();
+    let mut expected = Vec::new();
+    serializer.serialize(&sequential, &mut expected).unwrap();
+    let mut serialized = Vec::new();
+    serializer.serialize(&parallel, &mut serialized).unwrap();
+    assert_eq!(serialized, expected);
+
+    // a corrupted block fails the whole graph: skip its varint length
+    // and mark its first DEFLATE block with the reserved block type
+    let mut start = offsets[5];
+    while buffer[start] & 0x80 != 0 {
+        start += 1;
+    }
+    buffer[start + 1] = 0b111;
+    assert!(deserializer
+        .deserialize_parallel::<DeserializeError>(&buffer)
+        .is_err());
+}
+
+#[test]
+fn test_serialize_bounded() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 1);
+    let serializer = BootstrapableGraphSerializer::new();
+    let mut expected = Vec::new();
+    serializer.serialize(&graph, &mut expected).unwrap();
+
+    let mut buffer = Vec::new();
+    serializer
+        .serialize_bounded(&graph, &mut buffer, expected.len())
+        .unwrap();
+    assert_eq!(buffer, expected);
+
+    // the bound is exceeded by the third block: serialization stops there
+    let mut first_blocks = Vec::new();
+    serializer
+        .serialize(
+            &BootstrapableGraph {
+                final_blocks: graph.final_blocks[..2].to_vec(),
+            },
+            &mut first_blocks,
+        )
+        .unwrap();
+    let mut buffer = vec![1, 2, 3];
+    let err = serializer
+        .serialize_bounded(&graph, &mut buffer, first_blocks.len() + 1)
+        .unwrap_err();
+    assert!(
+        matches!(&err, SerializeError::TooLarge(message) if message.contains("after 3 final blocks")),
+        "{}",
+        err
+    );
+    assert_eq!(buffer, vec![1, 2, 3]);
+}
+
+#[test]
+fn test_serialize_bounded_merkle_root() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 1);
+    let serializer = BootstrapableGraphSerializer::new_with_merkle_root();
+    let mut expected = Vec::new();
+    serializer.serialize(&graph, &mut expected).unwrap();
+    assert_eq!(&expected[..32], graph.merkle_root().unwrap().to_bytes());
+
+    let mut buffer = Vec::new();
+    serializer
+        .serialize_bounded(&graph, &mut buffer, expected.len())
+        .unwrap();
+    assert_eq!(buffer, expected);
+
+    // the bound, which counts the merkle root, is checked from the first block on
+    let err = serializer
+        .serialize_bounded(&graph, &mut buffer, 33)
+        .unwrap_err();
+    assert!(
+        matches!(&err, SerializeError::TooLarge(message) if message.contains("after 1 final blocks")),
+        "{}",
+        err
+    );
+    assert_eq!(buffer, expected);
+}
+
+#[test]
+fn test_deserialize_impossible_block_count() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 1, 0);
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer[0], 4);
+
+    // 40 blocks are allowed by the limits but cannot fit in the 4 serialized ones
+    buffer[0] = 40;
+    let err = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 100)
+        .deserialize::<DeserializeError>(&buffer)
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Final block count too large for the remaining data"));
+}
+
+#[test]
+fn test_deserialize_genesis_only() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 0, 0);
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer[0], THREAD_COUNT);
+
+    let (rest, deserialized) =
+        BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 100)
+            .deserialize::<DeserializeError>(&buffer)
+            .unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(
+        deserialized
+            .final_blocks
+            .iter()
+            .map(|block| block.block.id)
+            .collect::<Vec<_>>(),
+        graph
+            .final_blocks
+            .iter()
+            .map(|block| block.block.id)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_highest_period() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 2, 0);
+    assert_eq!(graph.highest_period(0), Some(2));
+    assert_eq!(graph.highest_period(1), Some(2));
+    assert_eq!(graph.highest_period(THREAD_COUNT), None);
+
+    // dropping the oldest blocks keeps the highest periods
+    graph
+        .final_blocks
+        .retain(|block| block.block.content.header.content.slot.period > 0);
+    assert_eq!(graph.highest_period(0), Some(2));
+
+    // and so does a reserialization
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    let (_, deserialized) = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 10)
+        .deserialize::<DeserializeError>(&buffer)
+        .unwrap();
+    for thread in 0..THREAD_COUNT {
+        assert_eq!(
+            deserialized.highest_period(thread),
+            graph.highest_period(thread)
+        );
+    }
+}
+
+#[test]
+fn test_highest_period_after_pruning() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 3, 2);
+    let deserializer = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 10);
+
+    // a window starting above the genesis blocks drops them from the bottom
+    let mut buffer = Vec::new();
+    graph
+        .serialize_window(0..THREAD_COUNT, 1..u64::MAX, &mut buffer)
+        .unwrap();
+    let (_, (_, windowed)) = deserializer
+        .deserialize_window::<DeserializeError>(&buffer)
+        .unwrap();
+    assert_eq!(windowed.final_blocks.len(), graph.final_blocks.len() - 2);
+
+    // a fitness-bounded subgraph of the newest blocks, reserialized
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph.minimal_for_fitness(3).unwrap(), &mut buffer)
+        .unwrap();
+    let (_, minimal) = deserializer
+        .deserialize::<DeserializeError>(&buffer)
+        .unwrap();
+
+    for pruned in [windowed, minimal] {
+        for thread in 0..THREAD_COUNT {
+            assert_eq!(pruned.highest_period(thread), graph.highest_period(thread));
+        }
+    }
+}
+
+#[test]
+fn test_referenced_endorsement_ids() {
+    let keypair = KeyPair::generate(0).unwrap();
+    // genesis blocks have no endorsements, the 4 later blocks have 3 each
+    let graph = create_graph(&keypair, 2, 3);
+    let expected: PreHashSet<EndorsementId> = graph
+        .final_blocks
+        .iter()
+        .flat_map(|block| &block.block.content.header.content.endorsements)
+        .map(|endorsement| endorsement.id)
+        .collect();
+    assert_eq!(expected.len(), 12);
+    assert_eq!(graph.referenced_endorsement_ids(), expected);
+    assert_eq!(graph.final_blocks[0].endorsement_ids().count(), 0);
+    assert_eq!(graph.final_blocks[2].endorsement_ids().count(), 3);
+
+    assert!(create_graph(&keypair, 2, 0)
+        .referenced_endorsement_ids()
+        .is_empty());
+}
+
+#[test]
+fn test_streaming_checksum_verifier() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 3, 2);
+    for checksum in [ChecksumHash::Massa, ChecksumHash::Sha256] {
+        let mut buffer = Vec::new();
+        BootstrapableGraphSerializer::new_with_checksum(checksum)
+            .serialize(&graph, &mut buffer)
+            .unwrap();
+        let verify = |data: &[u8], chunk_sizes: &[usize]| {
+            let mut verifier = BootstrapableGraphVerifier::new();
+            let mut rest = data;
+            for chunk_size in chunk_sizes.iter().cycle() {
+                if rest.is_empty() {
+                    break;
+                }
+                let (chunk, next) = rest.split_at((*chunk_size).min(rest.len()));
+                verifier.update(chunk)?;
+                rest = next;
+            }
+            verifier.finish()
+        };
+
+        // chunk boundaries fall inside the header and inside the blocks
+        for chunk_sizes in [
+            &[1][..],
+            &[3, 0, 7][..],
+            &[40, 1000][..],
+            &[buffer.len()][..],
+        ] {
+            verify(&buffer, chunk_sizes).unwrap();
+        }
+
+        // a single flipped byte is detected, in the blocks or in the expected checksum
+        for index in [buffer.len() / 2, 1 + checksum.digest_len() / 2] {
+            let mut corrupted = buffer.clone();
+            corrupted[index] ^= 1;
+            assert!(matches!(
+                verify(&corrupted, &[5, 17]),
+                Err(GraphError::InvalidChecksum(_))
+            ));
+        }
+    }
+
+    // truncated header and unknown hash function
+    let verifier = BootstrapableGraphVerifier::new();
+    assert!(verifier.finish().is_err());
+    assert!(BootstrapableGraphVerifier::new().update(&[42]).is_err());
+}
+
+#[test]
+fn test_serialize_max_final_blocks() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 1, 0);
+
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new_with_max_final_blocks(4)
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+
+    let mut buffer = Vec::new();
+    let err = BootstrapableGraphSerializer::new_with_max_final_blocks(3)
+        .serialize(&graph, &mut buffer)
+        .unwrap_err();
+    assert!(
+        matches!(&err, SerializeError::NumberTooBig(message) if message == "4 final blocks, above the maximum of 3"),
+        "{}",
+        err
+    );
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_finality_proof() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 2, 0);
+    let root = graph.merkle_root().unwrap();
+    // the block of slot (1, 1) has 2 descendants of fitness 1
+    let block_id = graph.final_blocks[3].block.id;
+    let proof = graph.finality_proof(&block_id, 1).unwrap();
+    assert_eq!(proof.block.block.id, block_id);
+    assert_eq!(proof.descendants.len(), 2);
+    assert!(verify_finality_proof(&proof, &root, 1));
+    // the evidence does not hold for a higher threshold
+    assert!(!verify_finality_proof(&proof, &root, 2));
+
+    // forged proofs: another block, or a block claimed final while it is not
+    let mut forged = proof.clone();
+    forged.block = graph.final_blocks[2].clone();
+    assert!(!verify_finality_proof(&forged, &root, 1));
+    let mut forged = proof.clone();
+    forged.block.is_final = false;
+    assert!(!verify_finality_proof(&forged, &root, 1));
+
+    // forged evidence: a missing, repeated or unrelated descendant
+    let mut forged = proof.clone();
+    forged.descendants.pop();
+    assert!(!verify_finality_proof(&forged, &root, 1));
+    let mut forged = proof.clone();
+    forged.descendants[1] = forged.descendants[0].clone();
+    assert!(!verify_finality_proof(&forged, &root, 1));
+    let mut forged = proof;
+    let unrelated = graph.final_blocks[0].block.id;
+    forged.descendants[1] = (
+        graph.final_blocks[0].clone(),
+        graph.merkle_proof(&unrelated).unwrap().unwrap(),
+    );
+    assert!(!verify_finality_proof(&forged, &root, 1));
+
+    // unknown blocks, blocks with too few descendants or not final have no proof
+    let other_graph = create_graph(&keypair, 3, 0);
+    assert!(matches!(
+        graph.finality_proof(&other_graph.final_blocks[7].block.id, 1),
+        Err(GraphError::UnknownBlock(_))
+    ));
+    assert!(matches!(
+        graph.finality_proof(&block_id, 2),
+        Err(GraphError::NotFinal(_))
+    ));
+    graph.final_blocks[3].is_final = false;
+    assert!(matches!(
+        graph.finality_proof(&block_id, 1),
+        Err(GraphError::NotFinal(_))
+    ));
+}
+
+#[test]
+fn test_validate_genesis_presence() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 1, 0);
+    graph.validate_genesis_presence(THREAD_COUNT).unwrap();
+
+    // genesis of thread 1 dropped
+    graph.final_blocks.remove(1);
+    assert!(matches!(
+        graph.validate_genesis_presence(THREAD_COUNT),
+        Err(GraphError::MissingGenesis(threads)) if threads == vec![1]
+    ));
+    assert!(matches!(
+        graph.validate_genesis_presence(3),
+        Err(GraphError::MissingGenesis(threads)) if threads == vec![1, 2]
+    ));
+}
+
+#[test]
+fn test_deserialize_from_bufread() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 2);
+    let mut buffer = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut buffer)
+        .unwrap();
+    let deserializer = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 100);
+    let expected_ids: Vec<BlockId> = graph.final_blocks.iter().map(|b| b.block.id).collect();
+
+    // whole buffer, then chunks smaller than a block
+    for capacity in [buffer.len(), 7] {
+        let reader = BufReader::with_capacity(capacity, Cursor::new(&buffer));
+        let mut ids = Vec::new();
+        let count = deserializer
+            .deserialize_from_bufread(reader, |block| ids.push(block.block.id))
+            .unwrap();
+        assert_eq!(count as usize, expected_ids.len());
+        assert_eq!(ids, expected_ids);
+    }
+
+    // truncated stream
+    let reader = Cursor::new(&buffer[..buffer.len() - 1]);
+    assert!(matches!(
+        deserializer.deserialize_from_bufread(reader, |_| {}),
+        Err(GraphError::InvalidStream(_))
+    ));
+
+    // trailing data
+    buffer.push(0);
+    assert!(matches!(
+        deserializer.deserialize_from_bufread(Cursor::new(&buffer), |_| {}),
+        Err(GraphError::InvalidStream(_))
+    ));
+}
+
+#[test]
+fn test_deserialize_from_bufread_corrupt_block() {
+    let deserializer = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 100);
+
+    // a corrupt block is not read until the end of the stream
+    let mut stream = vec![1];
+    stream.resize(1 + 3 * MAX_BLOCK_SIZE as usize, 0xff);
+    let mut reader = Cursor::new(&stream);
+    assert!(matches!(
+        deserializer.deserialize_from_bufread(&mut reader, |_| {}),
+        Err(GraphError::InvalidStream(_))
+    ));
+    assert!(reader.position() <= 1 + MAX_BLOCK_SIZE as u64);
+
+    // the block count is bounded by the byte budget
+    let deserializer = BootstrapableGraphDeserializer::with_limits(BootstrapLimits {
+        thread_count: THREAD_COUNT,
+        max_bytes: 1000,
+        ..BootstrapLimits::default()
+    });
+    let err = deserializer
+        .deserialize_from_bufread(Cursor::new([100]), |_| {})
+        .unwrap_err();
+    assert!(err.to_string().contains("byte budget"));
+}
+
+#[test]
+fn test_sample_compression_ratio() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 40, 2);
+    let serializer = BootstrapableGraphSerializer::new();
+    let mut serialized = Vec::new();
+    serializer.serialize(&graph, &mut serialized).unwrap();
+    let mut compressed = Vec::new();
+    serializer
+        .serialize_compressed_blocks(&graph, &mut compressed)
+        .unwrap();
+    let full_ratio = compressed.len() as f32 / serialized.len() as f32;
+
+    let sampled_ratio = graph
+        .sample_compression_ratio(&ExportActiveBlockSerializer::new())
+        .unwrap();
+    assert!(
+        (sampled_ratio - full_ratio).abs() < 0.05,
+        "sampled {} full {}",
+        sampled_ratio,
+        full_ratio
+    );
+
+    let empty = BootstrapableGraph {
+        final_blocks: Vec::new(),
+    };
+    assert_eq!(
+        empty
+            .sample_compression_ratio(&ExportActiveBlockSerializer::new())
+            .unwrap(),
+        1.0
+    );
+}
+
+#[test]
+fn test_best_block() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let mut graph = create_graph(&keypair, 2, 0);
+
+    // all the blocks have a fitness of 1: the smallest id wins
+    let smallest_id = graph.final_blocks.iter().map(|b| b.block.id).min().unwrap();
+    assert_eq!(graph.best_block().unwrap().block.id, smallest_id);
+
+    // a block with endorsements has a higher fitness
+    let parents = vec![
+        (graph.final_blocks[4].block.id, 2),
+        (graph.final_blocks[5].block.id, 2),
+    ];
+    let best = create_export_active_block(&keypair, Slot::new(3, 1), parents, 2, 0);
+    let best_id = best.block.id;
+    graph.final_blocks.push(best);
+    assert_eq!(graph.best_block().unwrap().block.id, best_id);
+
+    assert!(BootstrapableGraph {
+        final_blocks: Vec::new()
+    }
+    .best_block()
+    .is_none());
+}
+
+#[test]
+fn test_serialize_window() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 3, 0);
+    let mut buffer = Vec::new();
+    graph.serialize_window(1..2, 1..3, &mut buffer).unwrap();
+
+    let deserializer = BootstrapableGraphDeserializer::new(get_block_deserializer_args(), 100);
+    let (rest, (window, windowed)) = deserializer
+        .deserialize_window::<DeserializeError>(&buffer)
+        .unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(
+        window,
+        GraphWindow {
+            threads: 1..2,
+            periods: 1..3,
+        }
+    );
+    // blocks at (1, 1) and (2, 1)
+    let slots: Vec<Slot> = windowed
+        .final_blocks
+        .iter()
+        .map(|b| b.block.content.header.content.slot)
+        .collect();
+    assert_eq!(slots, vec![Slot::new(1, 1), Slot::new(2, 1)]);
+
+    // a block outside of the declared window is rejected
+    let mut buffer = Vec::new();
+    graph.serialize_window(1..2, 1..3, &mut buffer).unwrap();
+    // thread range end
+    buffer[1] = 1;
+    assert!(deserializer
+        .deserialize_window::<DeserializeError>(&buffer)
+        .is_err());
+}
+
+#[test]
+fn test_serialize_annotated() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 1);
+    for serializer in [
+        BootstrapableGraphSerializer::new(),
+        BootstrapableGraphSerializer::new_with_merkle_root(),
+        BootstrapableGraphSerializer::new_with_checksum(ChecksumHash::Sha256),
+    ] {
+        let mut expected = Vec::new();
+        serializer.serialize(&graph, &mut expected).unwrap();
+        let (buffer, annotations) = serializer.serialize_annotated(&graph).unwrap();
+        assert_eq!(buffer, expected);
+
+        // contiguous ranges covering the whole buffer
+        let mut end = 0;
+        for (label, range) in &annotations {
+            assert_eq!(range.start, end, "gap or overlap before {}", label);
+            end = range.end;
+        }
+        assert_eq!(end, buffer.len());
+
+        // each range holds the bytes of its field
+        let field = |label: &str| {
+            let (_, range) = annotations
+                .iter()
+                .find(|(other, _)| other == label)
+                .unwrap_or_else(|| panic!("no range for {}", label));
+            assert!(range.start <= range.end, "inverted range for {}", label);
+            &buffer[range.clone()]
+        };
+        if let Some((label, _)) = annotations
+            .first()
+            .filter(|(label, _)| label == "merkle root")
+        {
+            assert_eq!(field(label), graph.merkle_root().unwrap().to_bytes());
+        }
+        let block_serializer = ExportActiveBlockSerializer::new();
+        for (index, block) in graph.final_blocks.iter().enumerate() {
+            let mut header = Vec::new();
+            SecureShareSerializer::new()
+                .serialize(&block.block.content.header, &mut header)
+                .unwrap();
+            let mut operations = Vec::new();
+            block_serializer
+                .serialize_body(block, &mut operations)
+                .unwrap();
+            // header, then parents and finality
+            let mut header_section = Vec::new();
+            block_serializer
+                .serialize_header(block, &mut header_section)
+                .unwrap();
+            let parents = &header_section[header.len()..header_section.len() - 1];
+
+            assert_eq!(field(&format!("block[{}].header", index)), header);
+            assert_eq!(field(&format!("block[{}].operations", index)), operations);
+            assert_eq!(field(&format!("block[{}].parents", index)), parents);
+            assert_eq!(
+                field(&format!("block[{}].is_final", index)),
+                [u8::from(block.is_final)]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_minimal_for_fitness() {
+    let keypair = KeyPair::generate(0).unwrap();
+    // thread 0 grows on the genesis blocks up to period 2,
+    // thread 1 has a block at period 1 that no block of thread 0 descends from
+    let genesis: Vec<_> = (0..THREAD_COUNT)
+        .map(|thread| create_export_active_block(&keypair, Slot::new(0, thread), Vec::new(), 0, 0))
+        .collect();
+    let genesis_parents: Vec<(BlockId, u64)> = genesis.iter().map(|b| (b.block.id, 0)).collect();
+    let a = create_export_active_block(&keypair, Slot::new(1, 0), genesis_parents.clone(), 2, 0);
+    let c = create_export_active_block(&keypair, Slot::new(1, 1), genesis_parents.clone(), 2, 0);
+    let b = create_export_active_block(
+        &keypair,
+        Slot::new(2, 0),
+        vec![(a.block.id, 1), genesis_parents[1]],
+        2,
+        0,
+    );
+    let mut graph = BootstrapableGraph {
+        final_blocks: genesis,
+    };
+    graph.final_blocks.extend([a.clone(), c.clone(), b.clone()]);
+    graph.validate_parent_closure().unwrap();
+
+    // the newest block is enough, along with its ancestors
+    let subgraph = graph.minimal_for_fitness(3).unwrap();
+    subgraph.validate_parent_closure().unwrap();
+    let ids: Vec<BlockId> = subgraph.final_blocks.iter().map(|b| b.block.id).collect();
+    assert_eq!(
+        ids,
+        vec![
+            graph.final_blocks[0].block.id,
+            graph.final_blocks[1].block.id,
+            a.block.id,
+            b.block.id
+        ]
+    );
+    let fitness: u64 = subgraph.thread_fitness(THREAD_COUNT).unwrap().iter().sum();
+    assert!(fitness >= 3);
+
+    // a second block is needed: the one with the next highest slot
+    let subgraph = graph.minimal_for_fitness(4).unwrap();
+    subgraph.validate_parent_closure().unwrap();
+    assert_eq!(subgraph.final_blocks.len(), graph.final_blocks.len());
+
+    assert_eq!(graph.minimal_for_fitness(0).unwrap().final_blocks.len(), 0);
+    assert!(matches!(
+        graph.minimal_for_fitness(12),
+        Err(GraphError::InsufficientFitness(12, 11))
+    ));
+
+    // without the genesis blocks, the ancestors of the newest block are missing
+    let pruned = BootstrapableGraph {
+        final_blocks: vec![a.clone(), c, b.clone()],
+    };
+    assert!(matches!(
+        pruned.validate_parent_closure(),
+        Err(GraphError::MissingParent(id, _)) if id == a.block.id
+    ));
+    assert!(matches!(
+        pruned.minimal_for_fitness(3),
+        Err(GraphError::MissingParent(id, _)) if id == b.block.id
+    ));
+}
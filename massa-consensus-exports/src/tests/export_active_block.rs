@@ -0,0 +1,61 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+use super::tools::{
+    create_export_active_block, create_graph, get_block_deserializer_args, THREAD_COUNT,
+};
+use crate::error::GraphError;
+use crate::export_active_block::{ExportActiveBlockDeserializer, ExportActiveBlockSerializer};
+use massa_models::slot::Slot;
+use massa_serialization::{DeserializeError, Deserializer, Serializer};
+use massa_signature::KeyPair;
+
+#[test]
+fn test_validate_thread_consistency() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 1, 2);
+    for export_active_block in &graph.final_blocks {
+        export_active_block
+            .validate_thread_consistency(THREAD_COUNT)
+            .unwrap();
+    }
+
+    let parents = graph.final_blocks[2].parents.clone();
+    let block = create_export_active_block(&keypair, Slot::new(2, 5), parents.clone(), 0, 0);
+    assert!(matches!(
+        block.validate_thread_consistency(THREAD_COUNT),
+        Err(GraphError::ThreadOutOfRange(id, 5, THREAD_COUNT)) if id == block.block.id
+    ));
+
+    let mut block = create_export_active_block(&keypair, Slot::new(2, 0), parents.clone(), 0, 0);
+    block.parents.truncate(1);
+    assert!(matches!(
+        block.validate_thread_consistency(THREAD_COUNT),
+        Err(GraphError::ThreadInconsistency(..))
+    ));
+
+    let mut block = create_export_active_block(&keypair, Slot::new(2, 0), parents, 0, 0);
+    block.parents.swap(0, 1);
+    assert!(matches!(
+        block.validate_thread_consistency(THREAD_COUNT),
+        Err(GraphError::ThreadInconsistency(..))
+    ));
+}
+
+#[test]
+fn test_deserialize_with_thread_validation() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 1, 2);
+    let deserializer =
+        ExportActiveBlockDeserializer::new_with_thread_validation(get_block_deserializer_args());
+    for export_active_block in &graph.final_blocks {
+        let mut buffer = Vec::new();
+        ExportActiveBlockSerializer::new()
+            .serialize(export_active_block, &mut buffer)
+            .unwrap();
+        let (rest, deserialized) = deserializer
+            .deserialize::<DeserializeError>(&buffer)
+            .unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(deserialized.block.id, export_active_block.block.id);
+    }
+}
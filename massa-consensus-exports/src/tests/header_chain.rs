@@ -0,0 +1,58 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+use super::tools::{create_graph, THREAD_COUNT};
+use crate::bootstrapable_graph::BootstrapableGraphSerializer;
+use crate::header_chain::{HeaderChainDeserializer, HeaderChainSerializer};
+use massa_models::{block_id::BlockId, prehash::PreHashMap};
+use massa_serialization::{DeserializeError, Deserializer, Serializer};
+use massa_signature::KeyPair;
+
+#[test]
+fn test_header_chain_roundtrip() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 3, 2);
+    let chain = graph.header_chain();
+    assert_eq!(chain.len(), graph.final_blocks.len());
+
+    let mut buffer = Vec::new();
+    HeaderChainSerializer::new()
+        .serialize(&chain, &mut buffer)
+        .unwrap();
+    let (rest, deserialized) = HeaderChainDeserializer::new(THREAD_COUNT, 100)
+        .deserialize::<DeserializeError>(&buffer)
+        .unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(deserialized, chain);
+
+    // the chain is much smaller than the full graph
+    let mut full = Vec::new();
+    BootstrapableGraphSerializer::new()
+        .serialize(&graph, &mut full)
+        .unwrap();
+    assert!(buffer.len() < full.len());
+
+    assert!(HeaderChainDeserializer::new(THREAD_COUNT, 2)
+        .deserialize::<DeserializeError>(&buffer)
+        .is_err());
+}
+
+#[test]
+fn test_header_chain_parent_dag() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 3, 1);
+    let chain = graph.header_chain();
+    let headers: PreHashMap<BlockId, _> = chain.iter().map(|header| (header.id, header)).collect();
+
+    for (header, export_active_block) in chain.iter().zip(&graph.final_blocks) {
+        assert_eq!(header.id, export_active_block.block.id);
+        assert_eq!(header.fitness, export_active_block.block.get_fitness());
+        assert_eq!(header.parents, export_active_block.parents);
+        // every parent resolves within the chain, at the recorded period and its own thread
+        for (thread, (parent_id, period)) in header.parents.iter().enumerate() {
+            let parent = headers[parent_id];
+            assert_eq!(parent.slot.period, *period);
+            assert_eq!(parent.slot.thread as usize, thread);
+            assert!(parent.slot < header.slot);
+        }
+    }
+}
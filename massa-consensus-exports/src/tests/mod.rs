@@ -0,0 +1,8 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+mod bootstrapable_graph;
+mod export_active_block;
+mod header_chain;
+#[cfg(feature = "protobuf")]
+mod protobuf;
+pub(crate) mod tools;
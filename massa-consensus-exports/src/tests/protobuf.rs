@@ -0,0 +1,65 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+use super::tools::{create_graph, THREAD_COUNT};
+use crate::bootstrapable_graph::{
+    BootstrapLimits, BootstrapableGraph, BootstrapableGraphSerializer,
+};
+use crate::error::GraphError;
+use crate::protobuf::BootstrapableGraphProto;
+use massa_serialization::Serializer;
+use massa_signature::KeyPair;
+use prost::Message;
+
+fn limits() -> BootstrapLimits {
+    BootstrapLimits {
+        thread_count: THREAD_COUNT,
+        max_bootstrap_blocks: 10,
+        ..BootstrapLimits::default()
+    }
+}
+
+#[test]
+fn test_protobuf_roundtrip() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 2);
+    let encoded = graph.to_protobuf().unwrap().encode_to_vec();
+    let decoded = BootstrapableGraphProto::decode(encoded.as_slice()).unwrap();
+    let converted = BootstrapableGraph::from_protobuf(&decoded, limits()).unwrap();
+
+    // same content as the binary form
+    let serializer = BootstrapableGraphSerializer::new();
+    let mut expected = Vec::new();
+    serializer.serialize(&graph, &mut expected).unwrap();
+    let mut serialized = Vec::new();
+    serializer.serialize(&converted, &mut serialized).unwrap();
+    assert_eq!(serialized, expected);
+}
+
+#[test]
+fn test_protobuf_invalid() {
+    let keypair = KeyPair::generate(0).unwrap();
+    let graph = create_graph(&keypair, 2, 0);
+
+    let mut proto = graph.to_protobuf().unwrap();
+    proto.final_blocks[2].parents[0].block_id.pop();
+    assert!(matches!(
+        BootstrapableGraph::from_protobuf(&proto, limits()),
+        Err(GraphError::InvalidProtobuf(_))
+    ));
+
+    // one parent more than the thread count
+    let mut proto = graph.to_protobuf().unwrap();
+    let parent = proto.final_blocks[2].parents[0].clone();
+    proto.final_blocks[2].parents.push(parent);
+    assert!(matches!(
+        BootstrapableGraph::from_protobuf(&proto, limits()),
+        Err(GraphError::InvalidProtobuf(_))
+    ));
+
+    let mut proto = graph.to_protobuf().unwrap();
+    proto.final_blocks[2].block.push(0);
+    assert!(matches!(
+        BootstrapableGraph::from_protobuf(&proto, limits()),
+        Err(GraphError::InvalidProtobuf(_))
+    ));
+}
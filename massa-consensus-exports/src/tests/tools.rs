@@ -0,0 +1,112 @@
+// Copyright (c) 2023 MASSA LABS <info@massa.net>
+
+use crate::bootstrapable_graph::BootstrapableGraph;
+use crate::export_active_block::ExportActiveBlock;
+use massa_hash::Hash;
+use massa_models::{
+    block::{Block, BlockDeserializerArgs, BlockSerializer},
+    block_header::{BlockHeader, BlockHeaderSerializer},
+    block_id::BlockId,
+    endorsement::{Endorsement, EndorsementSerializer},
+    operation::OperationId,
+    secure_share::{Id, SecureShareContent},
+    slot::Slot,
+};
+use massa_signature::KeyPair;
+
+/// Thread count of the graphs built by these tools
+pub const THREAD_COUNT: u8 = 2;
+
+/// Deserializer arguments matching the blocks built by these tools
+pub fn get_block_deserializer_args() -> BlockDeserializerArgs {
+    BlockDeserializerArgs {
+        thread_count: THREAD_COUNT,
+        max_operations_per_block: 16,
+        endorsement_count: 16,
+        max_denunciations_per_block_header: 16,
+        last_start_period: Some(0),
+    }
+}
+
+/// Creates a final `ExportActiveBlock` at `slot`.
+///
+/// `parents` must be empty for genesis blocks (period 0) and contain one entry per thread otherwise.
+/// The block embeds `endorsement_count` endorsements of its parent in its own thread
+/// and `operation_count` dummy operation ids.
+pub fn create_export_active_block(
+    keypair: &KeyPair,
+    slot: Slot,
+    parents: Vec<(BlockId, u64)>,
+    endorsement_count: u32,
+    operation_count: usize,
+) -> ExportActiveBlock {
+    let endorsements = (0..endorsement_count)
+        .map(|index| {
+            Endorsement::new_verifiable(
+                Endorsement {
+                    slot,
+                    index,
+                    endorsed_block: parents[slot.thread as usize].0,
+                },
+                EndorsementSerializer::new(),
+                keypair,
+            )
+            .unwrap()
+        })
+        .collect();
+    let header = BlockHeader::new_verifiable(
+        BlockHeader {
+            current_version: 0,
+            announced_version: 0,
+            slot,
+            parents: parents.iter().map(|(id, _)| *id).collect(),
+            operation_merkle_root: Hash::compute_from(&Vec::new()),
+            endorsements,
+            denunciations: Vec::new(),
+        },
+        BlockHeaderSerializer::new(),
+        keypair,
+    )
+    .unwrap();
+    let operations = (0..operation_count)
+        .map(|index| OperationId::new(Hash::compute_from(&index.to_be_bytes())))
+        .collect();
+    let block = Block::new_verifiable(
+        Block { header, operations },
+        BlockSerializer::new(),
+        keypair,
+    )
+    .unwrap();
+    ExportActiveBlock {
+        block,
+        parents,
+        is_final: true,
+    }
+}
+
+/// Creates a graph with one genesis block per thread followed by `periods` periods
+/// in which every block references the latest block of each thread.
+/// Each non-genesis block embeds `endorsement_count` endorsements.
+pub fn create_graph(keypair: &KeyPair, periods: u64, endorsement_count: u32) -> BootstrapableGraph {
+    let mut final_blocks = Vec::new();
+    let mut latest: Vec<(BlockId, u64)> = Vec::with_capacity(THREAD_COUNT as usize);
+    for thread in 0..THREAD_COUNT {
+        let block = create_export_active_block(keypair, Slot::new(0, thread), Vec::new(), 0, 0);
+        latest.push((block.block.id, 0));
+        final_blocks.push(block);
+    }
+    for period in 1..=periods {
+        for thread in 0..THREAD_COUNT {
+            let block = create_export_active_block(
+                keypair,
+                Slot::new(period, thread),
+                latest.clone(),
+                endorsement_count,
+                0,
+            );
+            latest[thread as usize] = (block.block.id, period);
+            final_blocks.push(block);
+        }
+    }
+    BootstrapableGraph { final_blocks }
+}
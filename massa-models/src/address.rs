@@ -732,4 +732,40 @@ mod test {
 
         assert_eq!(addr, addr2);
     }
+
+    #[test]
+    fn test_address_serialization_roundtrip() {
+        let hash = massa_hash::Hash::compute_from(&"ADDR".as_bytes());
+        for addr in [
+            Address::User(UserAddress::UserAddressV0(UserAddressV0(hash))),
+            Address::SC(SCAddress::SCAddressV0(SCAddressV0(hash))),
+        ] {
+            let mut buffer: Vec<u8> = vec![];
+            AddressSerializer::new()
+                .serialize(&addr, &mut buffer)
+                .unwrap();
+            let (rest, addr2): (&[u8], Address) = AddressDeserializer::new()
+                .deserialize::<massa_serialization::DeserializeError>(&buffer)
+                .unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(addr, addr2);
+        }
+    }
+
+    #[test]
+    fn test_address_unknown_category() {
+        let hash = massa_hash::Hash::compute_from(&"ADDR".as_bytes());
+        let addr = Address::User(UserAddress::UserAddressV0(UserAddressV0(hash)));
+        let mut buffer: Vec<u8> = vec![];
+        AddressSerializer::new()
+            .serialize(&addr, &mut buffer)
+            .unwrap();
+
+        // only the user (0) and smart contract (1) categories exist
+        buffer[0] = 2;
+        let res: Result<(&[u8], Address), _> =
+            AddressDeserializer::new()
+                .deserialize::<massa_serialization::DeserializeError>(&buffer);
+        assert!(res.is_err());
+    }
 }
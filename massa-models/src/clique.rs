@@ -11,7 +11,7 @@ use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::combinator::value;
 use nom::error::context;
-use nom::error::{ContextError, ParseError};
+use nom::error::{ContextError, ErrorKind, ParseError};
 use nom::multi::length_count;
 use nom::sequence::tuple;
 use nom::{IResult, Parser};
@@ -19,6 +19,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::block_id::BlockId;
 use crate::prehash::PreHashSet;
+use std::collections::HashSet;
 use std::ops::Bound::{Excluded, Included};
 
 /// Mutually compatible blocks in the graph
@@ -97,6 +98,7 @@ pub struct CliqueDeserializer {
     block_ids_length_deserializer: U32VarIntDeserializer,
     block_id_deserializer: HashDeserializer,
     fitness_deserializer: U64VarIntDeserializer,
+    known_block_ids: Option<PreHashSet<BlockId>>,
 }
 
 impl CliqueDeserializer {
@@ -109,6 +111,19 @@ impl CliqueDeserializer {
             ),
             block_id_deserializer: HashDeserializer::new(),
             fitness_deserializer: U64VarIntDeserializer::new(Included(0), Included(u64::MAX)),
+            known_block_ids: None,
+        }
+    }
+
+    /// Creates a `CliqueDeserializer` that rejects cliques referencing a block id
+    /// that is not in `known_block_ids`, e.g. the ids of the final blocks of a bootstrap graph
+    pub fn new_with_known_block_ids(
+        max_bootstrap_blocks: u32,
+        known_block_ids: PreHashSet<BlockId>,
+    ) -> Self {
+        Self {
+            known_block_ids: Some(known_block_ids),
+            ..Self::new(max_bootstrap_blocks)
         }
     }
 }
@@ -150,9 +165,20 @@ impl Deserializer<Clique> for CliqueDeserializer {
                         self.block_ids_length_deserializer.deserialize(input)
                     }),
                     context("Failed block_id deserialization", |input| {
-                        self.block_id_deserializer
+                        let (rest, block_id) = self
+                            .block_id_deserializer
                             .deserialize(input)
-                            .map(|(rest, hash)| (rest, BlockId(hash)))
+                            .map(|(rest, hash)| (rest, BlockId(hash)))?;
+                        match &self.known_block_ids {
+                            Some(known_block_ids) if !known_block_ids.contains(&block_id) => {
+                                Err(nom::Err::Failure(ContextError::add_context(
+                                    input,
+                                    "Unknown block id in clique",
+                                    ParseError::from_error_kind(input, ErrorKind::Verify),
+                                )))
+                            }
+                            _ => Ok((rest, block_id)),
+                        }
                     }),
                 ),
                 context("Failed fitness deserialization", |input| {
@@ -175,3 +201,245 @@ impl Deserializer<Clique> for CliqueDeserializer {
         .parse(buffer)
     }
 }
+
+/// Serializer for a list of `Clique`s
+#[derive(Default)]
+pub struct CliquesSerializer {
+    length_serializer: U32VarIntSerializer,
+    clique_serializer: CliqueSerializer,
+}
+
+impl CliquesSerializer {
+    /// Creates a `CliquesSerializer`
+    pub fn new() -> Self {
+        Self {
+            length_serializer: U32VarIntSerializer::new(),
+            clique_serializer: CliqueSerializer::new(),
+        }
+    }
+}
+
+impl Serializer<Vec<Clique>> for CliquesSerializer {
+    fn serialize(&self, value: &Vec<Clique>, buffer: &mut Vec<u8>) -> Result<(), SerializeError> {
+        self.length_serializer.serialize(
+            &value
+                .len()
+                .try_into()
+                .map_err(|_| SerializeError::NumberTooBig("Too many cliques".to_string()))?,
+            buffer,
+        )?;
+        for clique in value {
+            self.clique_serializer.serialize(clique, buffer)?;
+        }
+        Ok(())
+    }
+}
+
+/// What to do with a clique holding the same block ids as a previous clique of the list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateCliquePolicy {
+    /// fail the deserialization, as duplicated cliques likely indicate corrupted data
+    #[default]
+    Reject,
+    /// keep only the first of the duplicated cliques
+    Dedup,
+}
+
+/// Deserializer for a list of `Clique`s
+pub struct CliquesDeserializer {
+    length_deserializer: U32VarIntDeserializer,
+    clique_deserializer: CliqueDeserializer,
+    duplicate_policy: DuplicateCliquePolicy,
+}
+
+impl CliquesDeserializer {
+    /// Creates a `CliquesDeserializer` rejecting duplicated cliques
+    pub fn new(max_cliques: u32, max_bootstrap_blocks: u32) -> Self {
+        Self::new_with_duplicate_policy(
+            max_cliques,
+            max_bootstrap_blocks,
+            DuplicateCliquePolicy::default(),
+        )
+    }
+
+    /// Creates a `CliquesDeserializer` handling duplicated cliques according to `duplicate_policy`
+    pub fn new_with_duplicate_policy(
+        max_cliques: u32,
+        max_bootstrap_blocks: u32,
+        duplicate_policy: DuplicateCliquePolicy,
+    ) -> Self {
+        Self {
+            length_deserializer: U32VarIntDeserializer::new(Included(0), Included(max_cliques)),
+            clique_deserializer: CliqueDeserializer::new(max_bootstrap_blocks),
+            duplicate_policy,
+        }
+    }
+
+    /// Creates a `CliquesDeserializer` rejecting duplicated cliques and cliques referencing a block id
+    /// that is not in `known_block_ids`, e.g. the ids of the final blocks of a bootstrap graph
+    pub fn new_with_known_block_ids(
+        max_cliques: u32,
+        max_bootstrap_blocks: u32,
+        known_block_ids: PreHashSet<BlockId>,
+    ) -> Self {
+        Self {
+            clique_deserializer: CliqueDeserializer::new_with_known_block_ids(
+                max_bootstrap_blocks,
+                known_block_ids,
+            ),
+            ..Self::new(max_cliques, max_bootstrap_blocks)
+        }
+    }
+}
+
+impl Deserializer<Vec<Clique>> for CliquesDeserializer {
+    fn deserialize<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(
+        &self,
+        buffer: &'a [u8],
+    ) -> IResult<&'a [u8], Vec<Clique>, E> {
+        let (mut rest, length) = context("Failed cliques length deserialization", |input| {
+            self.length_deserializer.deserialize(input)
+        })
+        .parse(buffer)?;
+        let mut cliques = Vec::new();
+        let mut seen_block_ids: HashSet<Vec<BlockId>> = HashSet::new();
+        for _ in 0..length {
+            let (clique_rest, clique) = self.clique_deserializer.deserialize(rest)?;
+            let mut block_ids: Vec<BlockId> = clique.block_ids.iter().copied().collect();
+            block_ids.sort_unstable();
+            if seen_block_ids.insert(block_ids) {
+                cliques.push(clique);
+            } else if self.duplicate_policy == DuplicateCliquePolicy::Reject {
+                return Err(nom::Err::Failure(ContextError::add_context(
+                    rest,
+                    "Duplicate clique",
+                    ParseError::from_error_kind(rest, ErrorKind::Verify),
+                )));
+            }
+            rest = clique_rest;
+        }
+        Ok((rest, cliques))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use massa_hash::Hash;
+    use massa_serialization::DeserializeError;
+
+    fn duplicated_cliques() -> Vec<Clique> {
+        let block_ids: PreHashSet<BlockId> = (0..3u8)
+            .map(|i| BlockId(Hash::compute_from(&[i])))
+            .collect();
+        vec![
+            Clique {
+                block_ids: block_ids.clone(),
+                fitness: 10,
+                is_blockclique: true,
+            },
+            Clique {
+                block_ids: block_ids.iter().take(2).copied().collect(),
+                fitness: 5,
+                is_blockclique: false,
+            },
+            Clique {
+                block_ids,
+                fitness: 10,
+                is_blockclique: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_cliques_reject_duplicates() {
+        let mut buffer = Vec::new();
+        CliquesSerializer::new()
+            .serialize(&duplicated_cliques(), &mut buffer)
+            .unwrap();
+        assert!(CliquesDeserializer::new(10, 10)
+            .deserialize::<DeserializeError>(&buffer)
+            .is_err());
+
+        // without the duplicate
+        let mut cliques = duplicated_cliques();
+        cliques.pop();
+        let mut buffer = Vec::new();
+        CliquesSerializer::new()
+            .serialize(&cliques, &mut buffer)
+            .unwrap();
+        let (rest, deserialized) = CliquesDeserializer::new(10, 10)
+            .deserialize::<DeserializeError>(&buffer)
+            .unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(deserialized.len(), 2);
+    }
+
+    #[test]
+    fn test_cliques_dedup_duplicates() {
+        let mut buffer = Vec::new();
+        CliquesSerializer::new()
+            .serialize(&duplicated_cliques(), &mut buffer)
+            .unwrap();
+        let (rest, deserialized) =
+            CliquesDeserializer::new_with_duplicate_policy(10, 10, DuplicateCliquePolicy::Dedup)
+                .deserialize::<DeserializeError>(&buffer)
+                .unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(deserialized.len(), 2);
+        assert!(deserialized[0].is_blockclique);
+        assert_eq!(deserialized[1].block_ids.len(), 2);
+    }
+
+    #[test]
+    fn test_clique_unknown_block_id() {
+        let clique = duplicated_cliques().remove(0);
+        let mut buffer = Vec::new();
+        CliqueSerializer::new()
+            .serialize(&clique, &mut buffer)
+            .unwrap();
+
+        let (_, deserialized) =
+            CliqueDeserializer::new_with_known_block_ids(10, clique.block_ids.clone())
+                .deserialize::<DeserializeError>(&buffer)
+                .unwrap();
+        assert_eq!(deserialized.block_ids, clique.block_ids);
+
+        let mut known_block_ids = clique.block_ids.clone();
+        let unknown = *known_block_ids.iter().next().unwrap();
+        known_block_ids.remove(&unknown);
+        assert!(
+            CliqueDeserializer::new_with_known_block_ids(10, known_block_ids)
+                .deserialize::<DeserializeError>(&buffer)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_cliques_unknown_block_id() {
+        let mut cliques = duplicated_cliques();
+        cliques.pop();
+        let mut buffer = Vec::new();
+        CliquesSerializer::new()
+            .serialize(&cliques, &mut buffer)
+            .unwrap();
+        let known_block_ids = cliques[0].block_ids.clone();
+
+        let (rest, deserialized) =
+            CliquesDeserializer::new_with_known_block_ids(10, 10, known_block_ids.clone())
+                .deserialize::<DeserializeError>(&buffer)
+                .unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(deserialized.len(), 2);
+
+        // the second clique references a block id that is no longer known
+        let mut known_block_ids = known_block_ids;
+        let unknown = *cliques[1].block_ids.iter().next().unwrap();
+        known_block_ids.remove(&unknown);
+        assert!(
+            CliquesDeserializer::new_with_known_block_ids(10, 10, known_block_ids)
+                .deserialize::<DeserializeError>(&buffer)
+                .is_err()
+        );
+    }
+}
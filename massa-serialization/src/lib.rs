@@ -8,7 +8,7 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     combinator::value,
-    error::{ContextError, ParseError},
+    error::{ContextError, ErrorKind, ParseError},
     sequence::preceded,
     IResult, Parser,
 };
@@ -23,6 +23,8 @@ pub enum SerializeError {
     GeneralError(String),
     /// String too big {0},
     StringTooBig(String),
+    /// Serialized data too large: {0}
+    TooLarge(String),
 }
 
 #[derive(Clone, Error)]
@@ -176,7 +178,7 @@ pub trait Serializer<T> {
 }
 
 macro_rules! gen_varint {
-    ($($type:ident, $s:ident, $bs:ident, $ds:ident, $d:expr);*) => {
+    ($($type:ident, $s:ident, $bs:ident, $ds:ident, $d:expr, $max_len:literal);*) => {
         use std::ops::{Bound, RangeBounds};
         use nom::error::context;
         use unsigned_varint::nom as unsigned_nom;
@@ -234,6 +236,14 @@ macro_rules! gen_varint {
             impl Deserializer<$type> for $ds {
                 fn deserialize<'a, E: ParseError<&'a [u8]> + ContextError<&'a [u8]>>(&self, buffer: &'a [u8]) -> IResult<&'a [u8], $type, E> {
                     context(concat!("Failed ", stringify!($type), " deserialization"), |input: &'a [u8]| {
+                        // a varint holding a value of this type never has its continuation bit set past its maximum length
+                        if input.len() >= $max_len && input[..$max_len].iter().all(|byte| byte & 0x80 != 0) {
+                            return Err(nom::Err::Error(ContextError::add_context(
+                                input,
+                                concat!("Varint longer than ", $max_len, " bytes for ", stringify!($type)),
+                                ParseError::from_error_kind(input, nom::error::ErrorKind::TooLarge),
+                            )));
+                        }
                         let (rest, value) = unsigned_nom::$type(input).map_err(|_| nom::Err::Error(ParseError::from_error_kind(input, nom::error::ErrorKind::Fail)))?;
                         if !self.range.contains(&value) {
                             return Err(nom::Err::Error(ParseError::from_error_kind(input, nom::error::ErrorKind::Fail)));
@@ -247,9 +257,9 @@ macro_rules! gen_varint {
 }
 
 gen_varint! {
-u16, U16VarIntSerializer, u16_buffer, U16VarIntDeserializer, "`u16`";
-u32, U32VarIntSerializer, u32_buffer, U32VarIntDeserializer, "`u32`";
-u64, U64VarIntSerializer, u64_buffer, U64VarIntDeserializer, "`u64`"
+u16, U16VarIntSerializer, u16_buffer, U16VarIntDeserializer, "`u16`", 3;
+u32, U32VarIntSerializer, u32_buffer, U32VarIntDeserializer, "`u32`", 5;
+u64, U64VarIntSerializer, u64_buffer, U64VarIntDeserializer, "`u64`", 10
 }
 
 #[derive(Clone)]
@@ -392,3 +402,128 @@ impl Deserializer<bool> for BoolDeserializer {
         })(buffer)
     }
 }
+
+/// Wraps the parser of the elements of a sequence into a parser that fails
+/// if the key of an element is not strictly greater than the key of the previous element.
+///
+/// The returned parser remembers the key of the last element it parsed,
+/// so a new one must be created for each sequence:
+/// ```
+/// use massa_serialization::{ascending_by, DeserializeError, Deserializer, U64VarIntDeserializer, U64VarIntSerializer, Serializer};
+/// use nom::multi::count;
+/// use std::ops::Bound::Included;
+///
+/// let serializer = U64VarIntSerializer::new();
+/// let deserializer = U64VarIntDeserializer::new(Included(0), Included(u64::MAX));
+/// let mut buffer = Vec::new();
+/// for value in [1u64, 2, 2] {
+///     serializer.serialize(&value, &mut buffer).unwrap();
+/// }
+/// let element_parser = |input| deserializer.deserialize::<DeserializeError>(input);
+/// assert!(count(ascending_by(element_parser, |value: &u64| *value), 2)(&buffer[..]).is_ok());
+/// assert!(count(ascending_by(element_parser, |value: &u64| *value), 3)(&buffer[..]).is_err());
+/// ```
+pub fn ascending_by<'a, T, K, E, P, F>(
+    mut element_parser: P,
+    key: F,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], T, E>
+where
+    P: Parser<&'a [u8], T, E>,
+    F: Fn(&T) -> K,
+    K: Ord,
+    E: ParseError<&'a [u8]> + ContextError<&'a [u8]>,
+{
+    let mut previous_key: Option<K> = None;
+    move |input: &'a [u8]| {
+        let (rest, element) = element_parser.parse(input)?;
+        let element_key = key(&element);
+        if matches!(&previous_key, Some(previous_key) if element_key <= *previous_key) {
+            return Err(nom::Err::Failure(ContextError::add_context(
+                input,
+                "Elements are not in strictly ascending order",
+                ParseError::from_error_kind(input, ErrorKind::Verify),
+            )));
+        }
+        previous_key = Some(element_key);
+        Ok((rest, element))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::multi::length_count;
+    use std::ops::Bound::Included;
+
+    fn serialize_sequence(values: &[u64]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        U32VarIntSerializer::new()
+            .serialize(&(values.len() as u32), &mut buffer)
+            .unwrap();
+        for value in values {
+            U64VarIntSerializer::new()
+                .serialize(value, &mut buffer)
+                .unwrap();
+        }
+        buffer
+    }
+
+    fn deserialize_sequence(buffer: &[u8]) -> IResult<&[u8], Vec<u64>, DeserializeError> {
+        let count_deserializer = U32VarIntDeserializer::new(Included(0), Included(u32::MAX));
+        let value_deserializer = U64VarIntDeserializer::new(Included(0), Included(u64::MAX));
+        length_count(
+            |input| count_deserializer.deserialize(input),
+            ascending_by(
+                |input| value_deserializer.deserialize(input),
+                |value: &u64| *value,
+            ),
+        )(buffer)
+    }
+
+    #[test]
+    fn test_ascending_by_in_order() {
+        let buffer = serialize_sequence(&[1, 5, 300, 301]);
+        let (rest, values) = deserialize_sequence(&buffer).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(values, vec![1, 5, 300, 301]);
+        assert_eq!(
+            deserialize_sequence(&serialize_sequence(&[])).unwrap().1,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_ascending_by_out_of_order() {
+        let buffer = serialize_sequence(&[1, 300, 5]);
+        assert!(matches!(
+            deserialize_sequence(&buffer),
+            Err(nom::Err::Failure(_))
+        ));
+    }
+
+    #[test]
+    fn test_ascending_by_duplicate() {
+        let buffer = serialize_sequence(&[1, 5, 5]);
+        assert!(matches!(
+            deserialize_sequence(&buffer),
+            Err(nom::Err::Failure(_))
+        ));
+    }
+
+    #[test]
+    fn test_u32_varint_too_long() {
+        let deserializer = U32VarIntDeserializer::new(Included(0), Included(u32::MAX));
+        let (rest, value) = deserializer
+            .deserialize::<DeserializeError>(&[0xff, 0xff, 0xff, 0xff, 0x0f])
+            .unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(value, u32::MAX);
+
+        let err = deserializer
+            .deserialize::<DeserializeError>(&[0xff, 0xff, 0xff, 0xff, 0xff, 0x01])
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Varint longer than 5 bytes for u32"));
+    }
+}
@@ -968,6 +968,14 @@ impl Signature {
         }
     }
 
+    /// Return the total length after serialization of a signature made by the keypair of `public_key`
+    pub fn get_ser_len_for(public_key: &PublicKey) -> usize {
+        match public_key {
+            PublicKey::PublicKeyV0(_) => <Signature!["0"]>::ser_len(),
+            PublicKey::PublicKeyV1(_) => <Signature!["1"]>::ser_len(),
+        }
+    }
+
     /// Serialize a Signature into bytes.
     ///
     /// # Example
@@ -1050,6 +1058,11 @@ impl Signature {
 
     /// Return the total length after serialization
     pub fn get_ser_len(&self) -> usize {
+        Self::ser_len()
+    }
+
+    /// Return the total length after serialization of a signature of this version
+    pub fn ser_len() -> usize {
         Self::VERSION_VARINT_SIZE_BYTES + Self::SIGNATURE_SIZE_BYTES
     }
 